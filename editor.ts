@@ -1,11 +1,53 @@
-import { keymap, EditorView } from "@codemirror/view";
-import { EditorState } from "@codemirror/state";
+import {
+  keymap,
+  EditorView,
+  Decoration,
+  DecorationSet,
+} from "@codemirror/view";
+import { EditorState, StateEffect, StateField } from "@codemirror/state";
 import { lineNumbers } from "@codemirror/gutter";
 import { history, historyKeymap } from "@codemirror/history";
 import { defaultKeymap } from "@codemirror/commands";
 
 const EDITOR_DOCUMENT = "editor-document";
 
+/// Where an error is in the document. Lines and columns start at 1.
+export type ErrorPosition = { line: number; column: number; length: number };
+
+const setErrorsEffect = StateEffect.define<ErrorPosition[]>();
+
+const errorMark = Decoration.mark({ class: "cm-error" });
+
+// Keeps track of the underlined errors. They are moved along when the document
+// changes, until new errors are set.
+const errorField = StateField.define<DecorationSet>({
+  create() {
+    return Decoration.none;
+  },
+  update(errors, transaction) {
+    errors = errors.map(transaction.changes);
+    for (let effect of transaction.effects) {
+      if (effect.is(setErrorsEffect)) {
+        const ranges = effect.value.map((error) => {
+          const doc = transaction.state.doc;
+          const line = doc.line(Math.min(Math.max(error.line, 1), doc.lines));
+          const from = Math.min(line.from + error.column - 1, doc.length);
+          // Errors at the end of the document have no length, so the last
+          // character is underlined instead.
+          const to = Math.min(from + Math.max(error.length, 1), doc.length);
+          return errorMark.range(Math.max(Math.min(from, to - 1), 0), to);
+        });
+        errors = Decoration.set(
+          ranges.filter((range) => range.from < range.to),
+          true
+        );
+      }
+    }
+    return errors;
+  },
+  provide: (field) => EditorView.decorations.from(field),
+});
+
 /// This class sets up a code editor inside the given DOM element. You can read
 /// the current editor state using the `document` property, or listen to changes
 /// by passing in a `onChange` function to the options. The changes made inside
@@ -32,6 +74,7 @@ export class Editor {
         doc: window.localStorage.getItem(EDITOR_DOCUMENT) || undefined,
         extensions: [
           updateListener(),
+          errorField,
           lineNumbers(),
           history(),
           keymap.of([...defaultKeymap, ...historyKeymap]),
@@ -58,4 +101,9 @@ export class Editor {
   get document() {
     return this.editorView.state.doc.toString();
   }
+
+  /// Underlines the given errors, replacing any errors that were set before.
+  setErrors(errors: ErrorPosition[]) {
+    this.editorView.dispatch({ effects: setErrorsEffect.of(errors) });
+  }
 }
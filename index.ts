@@ -1,29 +1,63 @@
-import { Editor } from "./editor";
+import { Editor, ErrorPosition } from "./editor";
 
-type Error = { message: string };
+// Errors from the program include where they are in the code
+type Error = { message: string } & Partial<ErrorPosition>;
+
+const GAME_STATE = "game-state";
 
 import("./pkg/static_void.js").then((lib) => {
   const pauseButton = document.getElementById("pause-button")!;
   const runButton = document.getElementById("run-button")!;
+  const sceneSelect = document.getElementById(
+    "scene-select"
+  )! as HTMLSelectElement;
+  const restartButton = document.getElementById("restart-button")!;
+  const saveButton = document.getElementById("save-button")!;
+  const loadButton = document.getElementById("load-button")!;
+  const saveReplayButton = document.getElementById("save-replay-button")!;
+  const replayButton = document.getElementById("replay-button")!;
+  const replayInput = document.getElementById(
+    "replay-input"
+  )! as HTMLInputElement;
+  const speedSelect = document.getElementById(
+    "speed-select"
+  )! as HTMLSelectElement;
   const canvas = document.getElementsByTagName("canvas")[0];
   const editorElement = document.getElementById("editor")!;
   const editorErrors = document.getElementById("editor-errors")!;
+  const consoleElement = document.getElementById("console")!;
 
   let game = new lib.Game();
   let parserError = false;
+  let shownErrors = "[]";
 
   function showErrors(errors?: Error[]) {
+    // This is called on every frame, so only update when the errors change
+    const key = JSON.stringify(errors || []);
+    if (key === shownErrors) {
+      return;
+    }
+    shownErrors = key;
+
     if (errors && errors.length > 0) {
       editorErrors.classList.remove("hidden");
       editorErrors.innerHTML = "";
       for (let error of errors) {
         let paragraph = window.document.createElement("p");
-        paragraph.textContent = error.message;
+        paragraph.textContent =
+          error.line !== undefined
+            ? `${error.line}:${error.column}: ${error.message}`
+            : error.message;
         editorErrors.appendChild(paragraph);
       }
     } else {
       editorErrors.classList.add("hidden");
     }
+    editor.setErrors(
+      (errors || []).filter(
+        (error): error is Error & ErrorPosition => error.line !== undefined
+      )
+    );
   }
 
   function changeProgram(document: string) {
@@ -42,8 +76,6 @@ import("./pkg/static_void.js").then((lib) => {
 
   changeProgram(editor.document);
 
-  let isPaused = false;
-
   // Set canvas size attributes to match physical size of window
   canvas.setAttribute("height", `${window.innerHeight}`);
   canvas.setAttribute("width", `${window.innerWidth}`);
@@ -53,17 +85,81 @@ import("./pkg/static_void.js").then((lib) => {
 
   // Button click handlers
   pauseButton.addEventListener("click", function () {
-    isPaused = true;
+    game.set_paused(true);
     pauseButton.classList.add("hidden");
     runButton.classList.remove("hidden");
   });
 
   runButton.addEventListener("click", function () {
-    isPaused = false;
+    game.set_paused(false);
     pauseButton.classList.remove("hidden");
     runButton.classList.add("hidden");
   });
 
+  for (let name of game.list_scenes() as string[]) {
+    let option = window.document.createElement("option");
+    option.value = name;
+    option.textContent = name;
+    sceneSelect.appendChild(option);
+  }
+
+  // Switching scenes and restarting keep the current program
+  sceneSelect.addEventListener("change", function () {
+    const error = game.switch_scene(sceneSelect.value);
+    if (error) {
+      showErrors([{ message: error }]);
+    }
+  });
+
+  restartButton.addEventListener("click", function () {
+    game.restart();
+  });
+
+  speedSelect.addEventListener("change", function () {
+    game.set_time_scale(parseFloat(speedSelect.value));
+  });
+
+  // Snapshots of the game are kept in local storage, so they survive a reload
+  saveButton.addEventListener("click", function () {
+    window.localStorage.setItem(GAME_STATE, game.save_state());
+  });
+
+  loadButton.addEventListener("click", function () {
+    const state = window.localStorage.getItem(GAME_STATE);
+    if (state) {
+      const error = game.load_state(state);
+      if (error) {
+        showErrors([{ message: error }]);
+      }
+    }
+  });
+
+  // Replays are downloaded as a file, so that they can be attached to bug
+  // reports and played back to reproduce the exact same run
+  saveReplayButton.addEventListener("click", function () {
+    const blob = new Blob([game.save_replay()], { type: "application/json" });
+    const link = window.document.createElement("a");
+    link.href = URL.createObjectURL(blob);
+    link.download = "replay.json";
+    link.click();
+    URL.revokeObjectURL(link.href);
+  });
+
+  replayButton.addEventListener("click", function () {
+    replayInput.click();
+  });
+
+  replayInput.addEventListener("change", async function () {
+    const file = replayInput.files?.[0];
+    if (file) {
+      const error = game.load_replay(await file.text());
+      if (error) {
+        showErrors([{ message: error }]);
+      }
+    }
+    replayInput.value = "";
+  });
+
   // Update canvas dimension attributes on window resize
   window.addEventListener("resize", function () {
     canvas.setAttribute("height", `${window.innerHeight}`);
@@ -93,19 +189,32 @@ import("./pkg/static_void.js").then((lib) => {
     editor.editorView.contentDOM.setAttribute("contentEditable", "false");
   });
 
-  // Run game loop on each frame
-  function animate() {
-    if (!isPaused) {
-      let error = game.tick();
-      if (error) {
-        showErrors([error]);
-      } else if (!parserError) {
-        showErrors([]);
-      }
+  // Shows what the program has written with `LOG`, scrolled to the newest line
+  let shownConsole = "";
+  function showConsole(lines: string[]) {
+    const text = lines.join("\n");
+    if (text === shownConsole) {
+      return;
+    }
+    shownConsole = text;
+    consoleElement.textContent = text;
+    consoleElement.classList.toggle("hidden", lines.length === 0);
+    consoleElement.scrollTop = consoleElement.scrollHeight;
+  }
+
+  // Run game loop on each frame. The game keeps rendering while paused, it
+  // just stops the simulation.
+  function animate(timestamp: number) {
+    let error = game.tick(timestamp);
+    if (error) {
+      showErrors([error]);
+    } else if (!parserError) {
+      showErrors([]);
     }
+    showConsole(game.console_output());
 
-    requestAnimationFrame(() => animate());
+    requestAnimationFrame(animate);
   }
 
-  animate();
+  requestAnimationFrame(animate);
 });
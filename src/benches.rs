@@ -0,0 +1,186 @@
+// Benchmarks comparing iteration over archetype storage with the previous
+// layout, where every component type was a `Vec<Option<Box<dyn Any>>>` indexed
+// by entity. Run with `cargo bench`.
+extern crate test;
+
+use crate::world::World;
+use hashbrown::HashMap;
+use std::any::{Any, TypeId};
+use test::Bencher;
+
+const ENTITY_COUNT: usize = 10_000;
+
+struct Position(f32, f32);
+struct Velocity(f32, f32);
+struct Mass;
+struct Label;
+
+/// Minimal copy of the old component store, so we have something to compare
+/// against.
+struct SparseStore {
+    components: HashMap<TypeId, Vec<Option<Box<dyn Any>>>>,
+}
+
+impl SparseStore {
+    fn new() -> Self {
+        let mut components = HashMap::new();
+        components.insert(TypeId::of::<Position>(), vec![]);
+        components.insert(TypeId::of::<Velocity>(), vec![]);
+        components.insert(TypeId::of::<Mass>(), vec![]);
+        components.insert(TypeId::of::<Label>(), vec![]);
+        SparseStore { components }
+    }
+
+    fn create_entity(&mut self) {
+        for components in self.components.values_mut() {
+            components.push(None);
+        }
+    }
+
+    fn insert<T: 'static>(&mut self, component: T) {
+        let components = self.components.get_mut(&TypeId::of::<T>()).unwrap();
+        let index = components.len() - 1;
+        components[index] = Some(Box::new(component));
+    }
+
+    // Same as the old `Entities::get_components`
+    fn get_components<T: 'static>(&self) -> Vec<Option<&T>> {
+        self.components[&TypeId::of::<T>()]
+            .iter()
+            .map(|option| {
+                option
+                    .as_ref()
+                    .map(|value| value.downcast_ref::<T>().unwrap())
+            })
+            .collect()
+    }
+
+    // Same as the old `Query` implementation for `(&T, &U)`
+    fn query<T: 'static, U: 'static>(&self) -> Vec<(&T, &U)> {
+        let first = self.get_components::<T>().into_iter();
+        let second = self.get_components::<U>().into_iter();
+        first
+            .zip(second)
+            .filter_map(|option| match option {
+                (Some(a), Some(b)) => Some((a, b)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // Same as the old `QueryMut` implementation for `(&mut T, &U)`
+    fn query_mut<T: 'static, U: 'static>(&mut self) -> Vec<(&mut T, &U)> {
+        if let [Ok(first), Ok(second)] = self
+            .components
+            .get_each_mut([&TypeId::of::<T>(), &TypeId::of::<U>()])
+        {
+            let first = first.iter_mut().map(|option| {
+                option
+                    .as_mut()
+                    .map(|value| value.downcast_mut::<T>().unwrap())
+            });
+            let second = second.iter().map(|option| {
+                option
+                    .as_ref()
+                    .map(|value| value.downcast_ref::<U>().unwrap())
+            });
+            first
+                .zip(second)
+                .filter_map(|option| match option {
+                    (Some(a), Some(b)) => Some((a, b)),
+                    _ => None,
+                })
+                .collect()
+        } else {
+            vec![]
+        }
+    }
+}
+
+// Asteroids all have a position and velocity, but only some of them have a
+// mass. Every other entity is a label without any of the queried components,
+// which the old layout still had to skip over.
+fn populate_world() -> World {
+    let mut world = World::new();
+
+    for index in 0..ENTITY_COUNT {
+        let mut builder = world.create_entity();
+        if index % 2 == 0 {
+            builder.with_component(Label);
+            continue;
+        }
+        builder
+            .with_component(Position(index as f32, 0.0))
+            .with_component(Velocity(1.0, 1.0));
+        if index % 3 == 0 {
+            builder.with_component(Mass);
+        }
+    }
+
+    world
+}
+
+fn populate_sparse_store() -> SparseStore {
+    let mut store = SparseStore::new();
+
+    for index in 0..ENTITY_COUNT {
+        store.create_entity();
+        if index % 2 == 0 {
+            store.insert(Label);
+            continue;
+        }
+        store.insert(Position(index as f32, 0.0));
+        store.insert(Velocity(1.0, 1.0));
+        if index % 3 == 0 {
+            store.insert(Mass);
+        }
+    }
+
+    store
+}
+
+#[bench]
+fn bench_archetype_query_two(b: &mut Bencher) {
+    let world = populate_world();
+    b.iter(|| {
+        let mut sum = 0.0;
+        for (position, velocity) in world.query::<(&Position, &Velocity)>() {
+            sum += position.0 * velocity.0 + position.1 * velocity.1;
+        }
+        sum
+    });
+}
+
+#[bench]
+fn bench_sparse_query_two(b: &mut Bencher) {
+    let store = populate_sparse_store();
+    b.iter(|| {
+        let mut sum = 0.0;
+        for (position, velocity) in store.query::<Position, Velocity>() {
+            sum += position.0 * velocity.0 + position.1 * velocity.1;
+        }
+        sum
+    });
+}
+
+#[bench]
+fn bench_archetype_query_mut(b: &mut Bencher) {
+    let mut world = populate_world();
+    b.iter(|| {
+        for (position, velocity) in world.query_mut::<(&mut Position, &Velocity)>() {
+            position.0 += velocity.0;
+            position.1 += velocity.1;
+        }
+    });
+}
+
+#[bench]
+fn bench_sparse_query_mut(b: &mut Bencher) {
+    let mut store = populate_sparse_store();
+    b.iter(|| {
+        for (position, velocity) in store.query_mut::<Position, Velocity>() {
+            position.0 += velocity.0;
+            position.1 += velocity.1;
+        }
+    });
+}
@@ -0,0 +1,132 @@
+// Runs a scene with a ship program outside of the browser, and prints the
+// telemetry of the player's ship after every simulation step. Exits with a
+// non-zero status if the program runs into an error, so that it can be used to
+// test programs:
+//
+//     cargo run --bin void-run -- landing examples/launch_into_orbit.md --ticks 600
+
+use std::process::ExitCode;
+
+use static_void::mission::{built_in_scene, program_source, Mission, Telemetry};
+
+const USAGE: &str = "usage: void-run <scene> <program> [--ticks <n>] [--format csv|json]
+
+  <scene>    a scene file, or the name of a built-in scene
+  <program>  a program file, either the program itself or a Markdown file
+             with the program in a code block
+  --ticks    the number of simulation steps to run (default: 600)
+  --format   print telemetry as CSV (default) or as JSON lines";
+
+enum Format {
+    Csv,
+    Json,
+}
+
+struct Options {
+    scene: String,
+    program: String,
+    ticks: u64,
+    format: Format,
+}
+
+fn parse_options(arguments: Vec<String>) -> Result<Options, String> {
+    let mut paths = vec![];
+    let mut ticks = 600;
+    let mut format = Format::Csv;
+
+    let mut arguments = arguments.into_iter();
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "--ticks" => {
+                let value = arguments.next().ok_or("missing value for --ticks")?;
+                ticks = value
+                    .parse()
+                    .map_err(|_| format!("invalid number of ticks `{}`", value))?;
+            }
+            "--format" => {
+                format = match arguments.next().as_deref() {
+                    Some("csv") => Format::Csv,
+                    Some("json") => Format::Json,
+                    _ => return Err(String::from("--format should be `csv` or `json`")),
+                };
+            }
+            _ if argument.starts_with("--") => {
+                return Err(format!("unknown option `{}`", argument));
+            }
+            _ => paths.push(argument),
+        }
+    }
+
+    match <[String; 2]>::try_from(paths) {
+        Ok([scene, program]) => Ok(Options {
+            scene,
+            program,
+            ticks,
+            format,
+        }),
+        Err(_) => Err(String::from("expected a scene and a program")),
+    }
+}
+
+// Scenes can be given either as a path, or by the name of a built-in scene.
+fn read_scene(scene: &str) -> Result<String, String> {
+    if let Ok(text) = std::fs::read_to_string(scene) {
+        return Ok(text);
+    }
+    built_in_scene(scene)
+        .ok_or_else(|| format!("there is no scene file or built-in scene `{}`", scene))
+}
+
+fn print(telemetry: &Telemetry, format: &Format) {
+    match format {
+        Format::Csv => println!("{}", telemetry.to_csv()),
+        Format::Json => println!("{}", telemetry.to_json()),
+    }
+}
+
+fn main() -> ExitCode {
+    let arguments: Vec<String> = std::env::args().skip(1).collect();
+    if arguments.iter().any(|argument| argument == "--help") {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+
+    let options = match parse_options(arguments) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("error: {}\n\n{}", error, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    let mission = read_scene(&options.scene).and_then(|scene| {
+        let program = std::fs::read_to_string(&options.program)
+            .map_err(|error| format!("couldn't read `{}`: {}", options.program, error))?;
+        Mission::new(&scene, &program_source(&program))
+    });
+    let mut mission = match mission {
+        Ok(mission) => mission,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            return ExitCode::from(2);
+        }
+    };
+
+    if let Format::Csv = options.format {
+        println!("{}", Telemetry::csv_header());
+    }
+    print(&mission.telemetry(), &options.format);
+
+    for _ in 0..options.ticks {
+        mission.step();
+        let telemetry = mission.telemetry();
+        print(&telemetry, &options.format);
+
+        if let Some(error) = mission.error() {
+            eprintln!("error at tick {}: {}", telemetry.tick, error);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    ExitCode::SUCCESS
+}
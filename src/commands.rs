@@ -0,0 +1,95 @@
+use crate::entities::Entity;
+use crate::world::World;
+
+enum Command {
+    Despawn(Entity),
+    Apply(Box<dyn FnOnce(&mut World)>),
+}
+
+/// Systems can't make structural changes to the world (like removing an
+/// entity) while they're iterating over the result of a query, since the query
+/// borrows the world. Instead they can record the changes into this buffer
+/// using `world.commands()`, and the scene will apply them once the system is
+/// done. The commands are applied in the order they were recorded.
+#[derive(Default)]
+pub struct Commands {
+    queue: Vec<Command>,
+}
+
+impl Commands {
+    pub fn new() -> Self {
+        Commands { queue: vec![] }
+    }
+
+    /// Removes the given entity and all its components.
+    pub fn despawn(&mut self, entity: Entity) {
+        self.queue.push(Command::Despawn(entity));
+    }
+
+    /// Inserts a component for an existing entity.
+    pub fn insert_component<T: 'static>(&mut self, entity: Entity, component: T) {
+        self.queue.push(Command::Apply(Box::new(move |world| {
+            world.insert_component(entity, component)
+        })));
+    }
+
+    /// Applies all the recorded commands to the world, and empties the buffer.
+    pub fn apply(&mut self, world: &mut World) {
+        for command in self.queue.drain(..) {
+            match command {
+                Command::Despawn(entity) => world.remove_entity(entity),
+                Command::Apply(apply) => apply(world),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Size(f32);
+
+    #[test]
+    fn test_despawn() {
+        let mut world = World::new();
+        let entity = world.create_entity().with_component(Size(10.0)).entity();
+
+        let mut commands = Commands::new();
+        commands.despawn(entity);
+        commands.apply(&mut world);
+
+        assert!(!world.is_alive(entity));
+        assert!(world.query::<&Size>().is_empty());
+    }
+
+    #[test]
+    fn test_insert_component() {
+        let mut world = World::new();
+        let entity = world.create_entity().with_component(Size(10.0)).entity();
+
+        let mut commands = Commands::new();
+        commands.insert_component(entity, true);
+        assert_eq!(world.get::<bool>(entity), None);
+
+        commands.apply(&mut world);
+        assert_eq!(world.get::<bool>(entity), Some(&true));
+    }
+
+    #[test]
+    fn test_despawn_while_querying() {
+        let mut world = World::new();
+        world.create_entity().with_component(Size(10.0));
+        world.create_entity().with_component(Size(20.0));
+
+        for (entity, size) in world.query::<(Entity, &Size)>() {
+            if size.0 > 15.0 {
+                world.commands().despawn(entity);
+            }
+        }
+        world.apply_commands();
+
+        assert_eq!(world.query::<&Size>(), vec![&Size(10.0)]);
+    }
+}
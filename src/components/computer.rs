@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+
+use crate::interpreter::evaluator::DEFAULT_CPU_CYCLES;
+
+// The flight computer that runs a ship's program. Ships without one get a
+// computer with the default number of cycles.
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Computer {
+    /// The number of CPU cycles the program can use every simulation step.
+    /// Every statement, expression and loop iteration uses one cycle.
+    pub cycles: usize,
+}
+
+impl Default for Computer {
+    fn default() -> Self {
+        Computer {
+            cycles: DEFAULT_CPU_CYCLES,
+        }
+    }
+}
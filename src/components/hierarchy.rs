@@ -0,0 +1,28 @@
+use crate::components::rigid_body::Transform;
+use crate::entities::Entity;
+use serde::{Deserialize, Serialize};
+
+// Attaches an entity to another one, so that it moves and rotates along with
+// it. This is the component to insert or remove when changing the hierarchy,
+// `Children` is kept up to date by the transform propagation system.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Parent(pub Entity);
+
+// The entities that have this entity as their parent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Children(pub Vec<Entity>);
+
+// The transform of a child relative to its parent. Root entities without a
+// rigid body use it as their position in the world.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LocalTransform(pub Transform);
+
+// The transform of an entity in the world, computed from its parent's global
+// transform and its own local transform. Only entities that are part of a
+// hierarchy have one. The root is kept so that the renderer can interpolate
+// the whole hierarchy along with it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlobalTransform {
+    pub transform: Transform,
+    pub root: Entity,
+}
@@ -1,4 +1,6 @@
+pub mod computer;
 pub mod gravity;
+pub mod hierarchy;
 pub mod point;
 pub mod program;
 pub mod rigid_body;
@@ -1,29 +1,173 @@
 use crate::interpreter::ast::Program as ParsedProgram;
 use crate::interpreter::lexer::Lexer;
-use crate::interpreter::object::{Command, Environment, RuntimeError};
+use crate::interpreter::object::{Command, Environment, ProgramVariable, RuntimeError};
 use crate::interpreter::parser::{Parser, ParserError};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::{BTreeMap, VecDeque};
+
+/// The number of lines kept in a ship's console. The oldest lines are dropped
+/// once it's full.
+pub const CONSOLE_LINES: usize = 100;
 
 pub struct Program {
+    pub source: String,
     pub program: Result<ParsedProgram, Vec<ParserError>>,
     pub environment: Environment,
     pub commands: Vec<Command>,
     pub error: Option<RuntimeError>,
+    pub console: Console,
+}
+
+/// The lines the program has written with `LOG`, oldest first.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct Console {
+    lines: VecDeque<String>,
+}
+
+impl Console {
+    pub fn log(&mut self, line: String) {
+        if self.lines.len() >= CONSOLE_LINES {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line);
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        self.lines.iter().cloned().collect()
+    }
 }
 
 impl Program {
     pub fn new() -> Self {
         Program {
+            source: String::new(),
             program: Err(vec![]),
             environment: Environment::new(),
             commands: vec![],
             error: None,
+            console: Console::default(),
         }
     }
 
+    /// Parses a new source for the program. Static variables are only kept if
+    /// the source stays the same.
     pub fn update(&mut self, input: String) {
+        if input != self.source {
+            self.environment.reset();
+        }
         let lexer = Lexer::new(&input);
         let mut parser = Parser::new(lexer);
         let program = parser.parse_program();
         self.program = program;
+        self.source = input;
+    }
+}
+
+// Only the source and the static variables are stored in snapshots. Everything
+// else is either parsed from the source, or computed again the next time the
+// program runs. Programs without static variables are stored as just the
+// source, which is also how they are written in scene files.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum ProgramDefinition {
+    Source(String),
+    WithMemory {
+        source: String,
+        memory: BTreeMap<String, ProgramVariable>,
+    },
+}
+
+impl Serialize for Program {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let memory = self.environment.get_statics();
+        if memory.is_empty() {
+            self.source.serialize(serializer)
+        } else {
+            ProgramDefinition::WithMemory {
+                source: self.source.clone(),
+                memory,
+            }
+            .serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Program {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (source, memory) = match ProgramDefinition::deserialize(deserializer)? {
+            ProgramDefinition::Source(source) => (source, BTreeMap::new()),
+            ProgramDefinition::WithMemory { source, memory } => (source, memory),
+        };
+        let mut program = Program::new();
+        if !source.is_empty() {
+            program.update(source);
+        }
+        for (key, value) in memory {
+            program.environment.set_static(key, value.into());
+        }
+        Ok(program)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::interpreter::evaluator::Evaluator;
+    use crate::interpreter::object::Object;
+
+    fn run(program: &mut Program) -> Object {
+        program.environment.clear();
+        let mut evaluator = Evaluator::new();
+        evaluator.eval(program.program.as_ref().unwrap(), &mut program.environment)
+    }
+
+    #[test]
+    fn test_statics_reset_when_source_changes() {
+        let source = String::from("STATIC LET X = 0\nLET X = X + 1\nX");
+        let mut program = Program::new();
+        program.update(source.clone());
+        run(&mut program);
+        run(&mut program);
+
+        program.update(source.clone());
+        assert_eq!(run(&mut program), Object::Integer(3));
+
+        program.update(format!("{}\n", source));
+        assert_eq!(run(&mut program), Object::Integer(1));
+    }
+
+    #[test]
+    fn test_console() {
+        let mut console = Console::default();
+        for line in 0..CONSOLE_LINES + 5 {
+            console.log(line.to_string());
+        }
+
+        let lines = console.lines();
+        assert_eq!(lines.len(), CONSOLE_LINES);
+        assert_eq!(lines.first(), Some(&String::from("5")));
+        assert_eq!(lines.last(), Some(&(CONSOLE_LINES + 4).to_string()));
+    }
+
+    #[test]
+    fn test_serialize_statics() {
+        let mut program = Program::new();
+        assert_eq!(serde_json::to_string(&program).unwrap(), r#""""#);
+
+        program.update(String::from(
+            "STATIC LET X = 1.5\nSTATIC LET F = FUNC DO END\n",
+        ));
+        run(&mut program);
+        let json = serde_json::to_string(&program).unwrap();
+        assert_eq!(
+            json,
+            r#"{"source":"STATIC LET X = 1.5\nSTATIC LET F = FUNC DO END\n","memory":{"X":{"Float":1.5}}}"#
+        );
+
+        let mut restored: Program = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.environment.get("X"), Some(Object::Float(1.5)));
+        assert!(restored.environment.is_static("X"));
+        run(&mut restored);
+        assert_eq!(restored.environment.get("X"), Some(Object::Float(1.5)));
     }
 }
@@ -1,7 +1,9 @@
 use rapier2d::na::Vector2;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
 
+#[derive(Serialize, Deserialize)]
 pub struct RigidBody {
-    pub id: usize,
     pub transform: Transform,
     pub mass: f32,
     pub linear_velocity: Vector2<f32>,
@@ -9,12 +11,148 @@ pub struct RigidBody {
     pub physics_mode: PhysicsMode,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Transform {
     pub position: Vector2<f32>,
     pub rotation: f32, // radians, so 2Pi == 360 deg
 }
 
+impl Transform {
+    /// A transform that doesn't move or rotate anything.
+    pub fn identity() -> Transform {
+        Transform {
+            position: Vector2::new(0.0, 0.0),
+            rotation: 0.0,
+        }
+    }
+
+    /// Returns the given transform, which is relative to this one, relative to
+    /// whatever this transform is relative to. Used to turn the local
+    /// transform of a child into a global one using the parent's transform.
+    pub fn mul_transform(&self, local: &Transform) -> Transform {
+        let (sin, cos) = self.rotation.sin_cos();
+        Transform {
+            position: self.position
+                + Vector2::new(
+                    local.position.x * cos - local.position.y * sin,
+                    local.position.x * sin + local.position.y * cos,
+                ),
+            rotation: self.rotation + local.rotation,
+        }
+    }
+
+    /// Returns the transform that undoes this one, so that multiplying the two
+    /// gives the identity. Used to get the transform of a child relative to
+    /// its parent from their global transforms.
+    pub fn inverse(&self) -> Transform {
+        let (sin, cos) = self.rotation.sin_cos();
+        Transform {
+            position: Vector2::new(
+                -self.position.x * cos - self.position.y * sin,
+                self.position.x * sin - self.position.y * cos,
+            ),
+            rotation: -self.rotation,
+        }
+    }
+
+    /// Returns the transform in between this one and the other one, where an
+    /// alpha of 0.0 is this transform and 1.0 is the other one. The rotation
+    /// takes the shortest way around.
+    pub fn interpolate(&self, other: &Transform, alpha: f32) -> Transform {
+        let mut rotation_delta = (other.rotation - self.rotation) % (2.0 * PI);
+        if rotation_delta > PI {
+            rotation_delta -= 2.0 * PI;
+        } else if rotation_delta < -PI {
+            rotation_delta += 2.0 * PI;
+        }
+
+        Transform {
+            position: self.position + (other.position - self.position) * alpha,
+            rotation: self.rotation + rotation_delta * alpha,
+        }
+    }
+}
+
+// The transform of a rigid body at the previous simulation step. The simulation
+// runs at a fixed rate, so we use this to interpolate between the last two
+// steps when rendering faster than that.
+pub struct PreviousTransform(pub Transform);
+
+#[derive(Serialize, Deserialize)]
 pub enum PhysicsMode {
     Static,
     Dynamic,
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_interpolate() {
+        let from = Transform {
+            position: Vector2::new(0.0, 10.0),
+            rotation: 0.0,
+        };
+        let to = Transform {
+            position: Vector2::new(10.0, 20.0),
+            rotation: 1.0,
+        };
+
+        let transform = from.interpolate(&to, 0.25);
+        assert_eq!(transform.position, Vector2::new(2.5, 12.5));
+        assert_eq!(transform.rotation, 0.25);
+    }
+
+    #[test]
+    fn test_mul_transform() {
+        let parent = Transform {
+            position: Vector2::new(10.0, 20.0),
+            rotation: PI / 2.0,
+        };
+        let local = Transform {
+            position: Vector2::new(5.0, 0.0),
+            rotation: 0.5,
+        };
+
+        let transform = parent.mul_transform(&local);
+        assert!((transform.position - Vector2::new(10.0, 25.0)).magnitude() < 0.0001);
+        assert_eq!(transform.rotation, PI / 2.0 + 0.5);
+        assert_eq!(Transform::identity().mul_transform(&local), local);
+    }
+
+    #[test]
+    fn test_inverse() {
+        let parent = Transform {
+            position: Vector2::new(10.0, 20.0),
+            rotation: PI / 2.0,
+        };
+        let global = Transform {
+            position: Vector2::new(10.0, 25.0),
+            rotation: PI / 2.0 + 0.5,
+        };
+
+        let local = parent.inverse().mul_transform(&global);
+        assert!((local.position - Vector2::new(5.0, 0.0)).magnitude() < 0.0001);
+        assert!((local.rotation - 0.5).abs() < 0.0001);
+
+        let identity = parent.mul_transform(&parent.inverse());
+        assert!(identity.position.magnitude() < 0.0001);
+        assert_eq!(identity.rotation, 0.0);
+    }
+
+    #[test]
+    fn test_interpolate_rotation_wraps_around() {
+        let from = Transform {
+            position: Vector2::new(0.0, 0.0),
+            rotation: PI - 0.1,
+        };
+        let to = Transform {
+            position: Vector2::new(0.0, 0.0),
+            rotation: -PI + 0.1,
+        };
+
+        let transform = from.interpolate(&to, 0.5);
+        assert!((transform.rotation - PI).abs() < 0.0001);
+    }
+}
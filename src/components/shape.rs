@@ -1,14 +1,17 @@
 use crate::components::point::Point;
 use perlin2d::PerlinNoise2D;
+use serde::{Deserialize, Serialize};
 use std::f64::consts::PI;
 use std::fmt;
 
+#[derive(Serialize, Deserialize)]
 pub struct Shape {
     pub vertices: Vec<Point>,
     pub color: ColorRGBA, //Mosly for debug
     pub is_sensor: bool,
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct ColorRGBA {
     pub r: u8,
     pub g: u8,
@@ -1,7 +1,9 @@
 use rapier2d::na::Vector2;
+use serde::{Deserialize, Serialize};
 
 // The overall thruster system of a spacecraft
 
+#[derive(Serialize, Deserialize)]
 pub struct Thrusters {
     pub fuel: f64,
     pub fuel_max: f64,
@@ -47,6 +49,7 @@ impl Thrusters {
 
 // A single thruster
 
+#[derive(Serialize, Deserialize)]
 pub struct Thruster {
     pub max_thrust_force: f64,
     pub fuel_consumption_per_force: f64,
@@ -1,80 +1,487 @@
 use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
 use std::any::{Any, TypeId};
 
-/// A type-alias for our vector of components. Each item in the vector is either
-/// None or Some(Any). This is done so that we can use the index of the vector
-/// as the identifier for a entity. It's not the most performant having to
-/// potentially loop over a bunch of None values, so we might want to look into
-/// other data structures here like Archetypes.
-pub type Components = Vec<Option<Box<dyn Any>>>;
+/// A column stores all the components of a single type for an archetype. Each
+/// column is just a `Vec<T>`, but we need to be able to move components between
+/// archetypes without knowing the concrete type, so we hide it behind this
+/// trait and downcast to `Vec<T>` when we know the type again.
+pub trait Column {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    /// Removes the component at the given row and pushes it onto the other
+    /// column. The last component is moved into the removed row.
+    fn move_row(&mut self, row: usize, other: &mut dyn Column);
+    /// Removes and drops the component at the given row. The last component is
+    /// moved into the removed row.
+    fn remove_row(&mut self, row: usize);
+}
+
+impl<T: 'static> Column for Vec<T> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn move_row(&mut self, row: usize, other: &mut dyn Column) {
+        let component = self.swap_remove(row);
+        other
+            .as_any_mut()
+            .downcast_mut::<Vec<T>>()
+            .unwrap()
+            .push(component);
+    }
+
+    fn remove_row(&mut self, row: usize) {
+        self.swap_remove(row);
+    }
+}
+
+/// An archetype holds all the entities that have the exact same set of
+/// component types. The components are stored in one tightly packed column per
+/// type, so the component at row N in each column belongs to the entity at
+/// row N in the entities vector. Iterating over a component type is then just
+/// iterating over a slice for every archetype that contains it.
+pub struct Archetype {
+    types: Vec<TypeId>,
+    pub columns: HashMap<TypeId, Box<dyn Column>>,
+    // When each component was added and last changed, stored in the same
+    // order as the columns. Used by the `Added` and `Changed` query filters.
+    pub ticks: HashMap<TypeId, Vec<ComponentTicks>>,
+    pub entities: Vec<Entity>,
+}
+
+impl Archetype {
+    fn new(types: Vec<TypeId>, columns: HashMap<TypeId, Box<dyn Column>>) -> Self {
+        let ticks = types.iter().map(|id| (*id, vec![])).collect();
+        Archetype {
+            types,
+            columns,
+            ticks,
+            entities: vec![],
+        }
+    }
+
+    /// Returns true if the entities in this archetype has a component of the
+    /// given type.
+    pub fn has(&self, id: &TypeId) -> bool {
+        self.columns.contains_key(id)
+    }
+
+    /// Returns the column for the given component type, if the archetype has
+    /// one.
+    pub fn column<T: 'static>(&self) -> Option<&Vec<T>> {
+        self.columns
+            .get(&TypeId::of::<T>())
+            .map(|column| column.as_any().downcast_ref::<Vec<T>>().unwrap())
+    }
+
+    /// Returns a mutable reference to the column for the given component
+    /// type, if the archetype has one.
+    pub fn column_mut<T: 'static>(&mut self) -> Option<&mut Vec<T>> {
+        self.columns
+            .get_mut(&TypeId::of::<T>())
+            .map(|column| column.as_any_mut().downcast_mut::<Vec<T>>().unwrap())
+    }
+}
+
+/// The change ticks of a single component. Every time a system runs the world
+/// moves on to a new tick, so comparing these with the tick a system last ran
+/// at tells us if the component has been added or changed since then.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ComponentTicks {
+    pub added: u64,
+    pub changed: u64,
+}
+
+impl ComponentTicks {
+    fn new(tick: u64) -> Self {
+        ComponentTicks {
+            added: tick,
+            changed: tick,
+        }
+    }
+}
+
+/// A handle to a single entity. The index points to a slot that keeps track of
+/// where the components of the entity are stored, while the generation is
+/// bumped every time a slot is reused. That way a handle to a removed entity
+/// will never point to a new entity that happens to get the same index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Entity {
+    pub index: usize,
+    pub generation: u32,
+}
+
+/// Where the components of an entity are stored: the archetype, and the row
+/// within each of the archetype columns.
+#[derive(Debug, Clone, Copy)]
+struct Location {
+    archetype: usize,
+    row: usize,
+}
+
+/// Keeps track of the current generation for each slot, and where the entity
+/// is stored. A slot without a location is a tombstone waiting to be reused by
+/// the next call to `create_entity`.
+struct Slot {
+    generation: u32,
+    location: Option<Location>,
+}
 
 /// The entities struct is just a wrapper around our components store. It hides
 /// the underlying data structures, while allowing us to create and get
 /// components from both our World and Query types.
 pub struct Entities {
-    pub components: HashMap<TypeId, Components>,
+    pub archetypes: Vec<Archetype>,
+    archetype_ids: HashMap<Vec<TypeId>, usize>,
+    // Every component type we have seen so far, with a function that creates
+    // an empty column for each type. This is what allows us to create new
+    // archetypes for type-erased components.
+    components: HashMap<TypeId, fn() -> Box<dyn Column>>,
+    slots: Vec<Slot>,
+    free_list: Vec<usize>,
+    // Entities that have had components removed (or have been removed
+    // themselves), along with the tick it happened at.
+    removed: HashMap<TypeId, Vec<(Entity, u64)>>,
+    /// The tick used for any changes made right now.
+    pub change_tick: u64,
+    /// The tick the currently running system last ran at. Anything that
+    /// changed after this tick is considered new to the system.
+    pub last_change_tick: u64,
 }
 
 impl Entities {
     pub fn new() -> Self {
+        // New entities don't have any components, so they all start out in
+        // the empty archetype at index 0.
+        let mut archetype_ids = HashMap::new();
+        archetype_ids.insert(vec![], 0);
+
         Entities {
+            archetypes: vec![Archetype::new(vec![], HashMap::new())],
+            archetype_ids,
             components: HashMap::new(),
+            slots: vec![],
+            free_list: vec![],
+            removed: HashMap::new(),
+            change_tick: 1,
+            last_change_tick: 0,
         }
     }
 
-    /// Register a component type by creating an empty vector for that type.
-    pub fn register_component<T: 'static>(&mut self) {
-        let id = TypeId::of::<T>();
-        self.components.insert(id, vec![]);
+    /// Create an entity and return a handle to it. If any entities have been
+    /// removed we reuse one of their slots with a new generation.
+    pub fn create_entity(&mut self) -> Entity {
+        let index = match self.free_list.pop() {
+            Some(index) => index,
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    location: None,
+                });
+                self.slots.len() - 1
+            }
+        };
+
+        let entity = Entity {
+            index,
+            generation: self.slots[index].generation,
+        };
+        self.place_in_empty_archetype(entity);
+        entity
     }
 
-    /// Create an entity by inserting empty values in the vectors for each
-    /// component type.
-    pub fn create_entity(&mut self) {
-        for (_key, value) in self.components.iter_mut() {
-            value.push(None);
+    /// Creates an entity with the given handle, so that any handles stored in
+    /// components stay valid when restoring a snapshot. Returns false if the
+    /// slot is already in use. This leaves the free list out of date, so
+    /// `rebuild_free_list` has to be called before creating any other entities.
+    pub fn create_entity_at(&mut self, entity: Entity) -> bool {
+        while self.slots.len() <= entity.index {
+            self.slots.push(Slot {
+                generation: 0,
+                location: None,
+            });
+        }
+
+        if self.slots[entity.index].location.is_some() {
+            return false;
         }
+
+        self.slots[entity.index].generation = entity.generation;
+        self.place_in_empty_archetype(entity);
+        true
     }
 
-    /// Insert a component of a given type. It's important to call
-    /// `register_component` and `create_entity` before calling this method.
-    pub fn insert_component<T: 'static>(&mut self, component: T) {
-        let id = TypeId::of::<T>();
-        let components = self.components.get_mut(&id).unwrap();
-        let index = components.len() - 1;
-        components[index] = Some(Box::new(component));
+    /// Marks every slot without an entity as free to use again.
+    pub fn rebuild_free_list(&mut self) {
+        self.free_list = (0..self.slots.len())
+            .rev()
+            .filter(|index| self.slots[*index].location.is_none())
+            .collect();
+    }
+
+    /// The number of slots, including the ones that are free to use.
+    pub fn slot_count(&self) -> usize {
+        self.slots.len()
     }
 
-    /// Get a reference to all the components of a given type.
-    pub fn get_components<T: 'static>(&self) -> Vec<Option<&T>> {
-        // Generate a unique identifier based on the generic type
+    fn place_in_empty_archetype(&mut self, entity: Entity) {
+        let empty = &mut self.archetypes[0];
+        empty.entities.push(entity);
+        self.slots[entity.index].location = Some(Location {
+            archetype: 0,
+            row: empty.entities.len() - 1,
+        });
+    }
+
+    /// Returns true if the entity has not been removed since it was created.
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.location(entity).is_some()
+    }
+
+    /// Insert a component of a given type for the given entity. If the entity
+    /// already has a component of this type it will be replaced, otherwise the
+    /// entity is moved to the archetype that includes the new type. Inserting
+    /// a component for an entity that has been removed does nothing.
+    pub fn insert_component<T: 'static>(&mut self, entity: Entity, component: T) {
+        let location = match self.location(entity) {
+            Some(location) => location,
+            None => return,
+        };
+
         let id = TypeId::of::<T>();
-        // Look for all component for that type identifier. Downcast each value
-        // to the given generic type.
+        let archetype = &mut self.archetypes[location.archetype];
+        if let Some(column) = archetype.column_mut::<T>() {
+            column[location.row] = component;
+            archetype.ticks.get_mut(&id).unwrap()[location.row].changed = self.change_tick;
+            return;
+        }
+
+        // The first time we see a component type we need to remember how to
+        // create columns for it.
         self.components
-            .get(&id)
+            .entry(id)
+            .or_insert(|| Box::new(Vec::<T>::new()) as Box<dyn Column>);
+
+        let mut types = self.archetypes[location.archetype].types.clone();
+        types.push(id);
+        types.sort();
+
+        let target = self.find_or_create_archetype(types);
+        let row = self.move_entity(location, target);
+        self.archetypes[target]
+            .column_mut::<T>()
             .unwrap()
-            .into_iter()
-            .map(|option| {
-                option
-                    .as_ref()
-                    .map(|value| value.downcast_ref::<T>().unwrap())
-            })
-            .collect()
+            .push(component);
+        self.archetypes[target]
+            .ticks
+            .get_mut(&id)
+            .unwrap()
+            .push(ComponentTicks::new(self.change_tick));
+        self.slots[entity.index].location = Some(Location {
+            archetype: target,
+            row,
+        });
+    }
+
+    /// Get a reference to the component of the given type for a single entity.
+    /// Returns None if the entity has been removed or doesn't have the
+    /// component.
+    pub fn get_component<T: 'static>(&self, entity: Entity) -> Option<&T> {
+        let location = self.location(entity)?;
+        self.archetypes[location.archetype]
+            .column::<T>()
+            .map(|column| &column[location.row])
+    }
+
+    /// Get a mutable reference to the component of the given type for a single
+    /// entity. The component is marked as changed, like in a mutable query.
+    pub fn get_component_mut<T: 'static>(&mut self, entity: Entity) -> Option<&mut T> {
+        let location = self.location(entity)?;
+        let archetype = &mut self.archetypes[location.archetype];
+        archetype.ticks.get_mut(&TypeId::of::<T>())?[location.row].changed = self.change_tick;
+        archetype
+            .column_mut::<T>()
+            .map(|column| &mut column[location.row])
     }
 
-    /// Removes all components for a given entity identifier.
-    pub fn remove_entity(&mut self, entity_id: usize) {
-        for components in self.components.values_mut() {
-            components.remove(entity_id);
+    /// Returns true if the entity is alive and has a component of the given
+    /// type.
+    pub fn has_component<T: 'static>(&self, entity: Entity) -> bool {
+        match self.location(entity) {
+            Some(location) => self.archetypes[location.archetype].has(&TypeId::of::<T>()),
+            None => false,
         }
     }
 
-    /// Removes the component of the given type for a specific entity.
-    pub fn remove_component<T: 'static>(&mut self, entity_id: usize) {
+    /// Removes all components for a given entity and leaves a tombstone in its
+    /// slot. The slot is recycled by the next call to `create_entity` with a
+    /// new generation, so any handles to the removed entity become stale.
+    pub fn remove_entity(&mut self, entity: Entity) {
+        let location = match self.location(entity) {
+            Some(location) => location,
+            None => return,
+        };
+
+        let archetype = &mut self.archetypes[location.archetype];
+        for column in archetype.columns.values_mut() {
+            column.remove_row(location.row);
+        }
+        for ticks in archetype.ticks.values_mut() {
+            ticks.remove_row(location.row);
+        }
+        for id in archetype.types.iter() {
+            self.removed
+                .entry(*id)
+                .or_default()
+                .push((entity, self.change_tick));
+        }
+        archetype.entities.swap_remove(location.row);
+        self.update_moved_entity(location);
+
+        let slot = &mut self.slots[entity.index];
+        slot.location = None;
+        slot.generation += 1;
+        self.free_list.push(entity.index);
+    }
+
+    /// Removes the component of the given type for a specific entity. The
+    /// entity is moved to the archetype without the removed type.
+    pub fn remove_component<T: 'static>(&mut self, entity: Entity) {
+        let location = match self.location(entity) {
+            Some(location) => location,
+            None => return,
+        };
+
         let id = TypeId::of::<T>();
-        let components = self.components.get_mut(&id).unwrap();
-        components[entity_id] = None;
+        if !self.archetypes[location.archetype].has(&id) {
+            return;
+        }
+
+        // Drop the component first, so that the rest of the components can be
+        // moved over to the new archetype.
+        let archetype = &mut self.archetypes[location.archetype];
+        archetype
+            .columns
+            .get_mut(&id)
+            .unwrap()
+            .remove_row(location.row);
+        archetype
+            .ticks
+            .get_mut(&id)
+            .unwrap()
+            .remove_row(location.row);
+        self.removed
+            .entry(id)
+            .or_default()
+            .push((entity, self.change_tick));
+
+        let mut types = self.archetypes[location.archetype].types.clone();
+        types.retain(|type_id| *type_id != id);
+
+        let target = self.find_or_create_archetype(types);
+        let row = self.move_entity(location, target);
+        self.slots[entity.index].location = Some(Location {
+            archetype: target,
+            row,
+        });
+    }
+
+    /// Returns the entities that have had a component of the given type
+    /// removed since the last change tick, including removed entities.
+    pub fn removed_components<T: 'static>(&self) -> Vec<Entity> {
+        match self.removed.get(&TypeId::of::<T>()) {
+            Some(removed) => removed
+                .iter()
+                .filter(|(_, tick)| *tick > self.last_change_tick)
+                .map(|(entity, _)| *entity)
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /// Forgets about removed components up until the given tick.
+    pub fn clear_removed_components(&mut self, tick: u64) {
+        for removed in self.removed.values_mut() {
+            removed.retain(|(_, removed_tick)| *removed_tick > tick);
+        }
+    }
+
+    fn location(&self, entity: Entity) -> Option<Location> {
+        let slot = self.slots.get(entity.index)?;
+        if slot.generation == entity.generation {
+            slot.location
+        } else {
+            None
+        }
+    }
+
+    /// Returns the index of the archetype with the given (sorted) set of
+    /// component types, creating it if it doesn't exist yet.
+    fn find_or_create_archetype(&mut self, types: Vec<TypeId>) -> usize {
+        if let Some(index) = self.archetype_ids.get(&types) {
+            return *index;
+        }
+
+        let columns = types
+            .iter()
+            .map(|id| (*id, self.components[id]()))
+            .collect();
+
+        self.archetypes.push(Archetype::new(types.clone(), columns));
+        self.archetype_ids.insert(types, self.archetypes.len() - 1);
+        self.archetypes.len() - 1
+    }
+
+    /// Moves all the components at the given location over to the target
+    /// archetype, and returns the row the entity ended up at. Any columns that
+    /// don't exist in the target archetype must be removed beforehand, and
+    /// any new columns must be pushed to afterwards.
+    fn move_entity(&mut self, location: Location, target: usize) -> usize {
+        let (source, target) = self.archetype_pair(location.archetype, target);
+
+        for (id, column) in source.columns.iter_mut() {
+            if let Some(target_column) = target.columns.get_mut(id) {
+                column.move_row(location.row, target_column.as_mut());
+            }
+        }
+        for (id, ticks) in source.ticks.iter_mut() {
+            if let Some(target_ticks) = target.ticks.get_mut(id) {
+                ticks.move_row(location.row, target_ticks);
+            }
+        }
+
+        let entity = source.entities.swap_remove(location.row);
+        target.entities.push(entity);
+        let row = target.entities.len() - 1;
+
+        self.update_moved_entity(location);
+        row
+    }
+
+    /// Removing a row means the last entity in the archetype was swapped into
+    /// its place, so we need to update where that entity is stored.
+    fn update_moved_entity(&mut self, location: Location) {
+        if let Some(moved) = self.archetypes[location.archetype]
+            .entities
+            .get(location.row)
+        {
+            self.slots[moved.index].location = Some(location);
+        }
+    }
+
+    fn archetype_pair(&mut self, first: usize, second: usize) -> (&mut Archetype, &mut Archetype) {
+        if first < second {
+            let (left, right) = self.archetypes.split_at_mut(second);
+            (&mut left[first], &mut right[0])
+        } else {
+            let (left, right) = self.archetypes.split_at_mut(first);
+            (&mut right[0], &mut left[second])
+        }
     }
 }
 
@@ -83,84 +490,183 @@ mod test {
     use super::*;
 
     #[test]
-    fn test_register_component() {
+    fn test_insert_component_registers_type() {
         let mut entities = Entities::new();
-        entities.register_component::<u32>();
-        entities.register_component::<isize>();
+        let entity = entities.create_entity();
+        entities.insert_component(entity, 1_u32);
 
         assert!(entities.components.contains_key(&TypeId::of::<u32>()));
-        assert!(entities.components.contains_key(&TypeId::of::<isize>()));
+        assert!(!entities.components.contains_key(&TypeId::of::<isize>()));
     }
 
     #[test]
     fn test_create_entity() {
         let mut entities = Entities::new();
-        entities.register_component::<u32>();
-        entities.register_component::<isize>();
-        entities.create_entity();
+        let entity = entities.create_entity();
 
-        let u32s = entities.components.get(&TypeId::of::<u32>()).unwrap();
-        let isizes = entities.components.get(&TypeId::of::<u32>()).unwrap();
-
-        assert_eq!(u32s.len(), 1);
-        assert_eq!(isizes.len(), 1);
-        assert!(u32s[0].is_none());
-        assert!(isizes[0].is_none());
+        assert_eq!(
+            entity,
+            Entity {
+                index: 0,
+                generation: 0
+            }
+        );
+        assert!(entities.is_alive(entity));
+        assert_eq!(entities.archetypes[0].entities, vec![entity]);
+        assert_eq!(entities.get_component::<u32>(entity), None);
     }
 
     #[test]
     fn test_insert_component() {
         let mut entities = Entities::new();
-        entities.register_component::<u32>();
-        entities.register_component::<isize>();
-        entities.create_entity();
-        entities.insert_component(1_u32);
-        entities.insert_component(2_isize);
+        let entity = entities.create_entity();
+        entities.insert_component(entity, 1_u32);
+        entities.insert_component(entity, 2_isize);
+
+        assert_eq!(*entities.get_component::<u32>(entity).unwrap(), 1);
+        assert_eq!(*entities.get_component::<isize>(entity).unwrap(), 2);
+        // One archetype for (), (u32) and (u32, isize)
+        assert_eq!(entities.archetypes.len(), 3);
+        assert_eq!(entities.archetypes[2].entities, vec![entity]);
+    }
 
-        let u32s = entities.get_components::<u32>();
-        let isizes = entities.get_components::<isize>();
+    #[test]
+    fn test_insert_component_replaces_existing() {
+        let mut entities = Entities::new();
+        let entity = entities.create_entity();
+        entities.insert_component(entity, 1_u32);
+        entities.insert_component(entity, 2_u32);
 
-        assert_eq!(u32s.len(), 1);
-        assert_eq!(isizes.len(), 1);
-        assert_eq!(*u32s[0].unwrap(), 1);
-        assert_eq!(*isizes[0].unwrap(), 2);
+        assert_eq!(*entities.get_component::<u32>(entity).unwrap(), 2);
+        assert_eq!(entities.archetypes[1].column::<u32>().unwrap().len(), 1);
     }
 
     #[test]
-    fn test_remove_component() {
+    fn test_get_component_mut() {
         let mut entities = Entities::new();
-        entities.register_component::<u32>();
-        entities.register_component::<isize>();
-        entities.create_entity();
-        entities.insert_component(1_u32);
-        entities.insert_component(2_isize);
+        let entity = entities.create_entity();
+        entities.insert_component(entity, 1_u32);
 
-        entities.remove_component::<u32>(0);
+        *entities.get_component_mut::<u32>(entity).unwrap() += 1;
 
-        let u32s = entities.get_components::<u32>();
-        let isizes = entities.get_components::<isize>();
+        assert_eq!(*entities.get_component::<u32>(entity).unwrap(), 2);
+        assert_eq!(entities.get_component_mut::<isize>(entity), None);
+    }
+
+    #[test]
+    fn test_has_component() {
+        let mut entities = Entities::new();
+        let entity = entities.create_entity();
+        entities.insert_component(entity, 1_u32);
+
+        assert!(entities.has_component::<u32>(entity));
+        assert!(!entities.has_component::<isize>(entity));
+
+        entities.remove_entity(entity);
+        assert!(!entities.has_component::<u32>(entity));
+    }
+
+    #[test]
+    fn test_insert_component_reuses_archetype() {
+        let mut entities = Entities::new();
+
+        let first = entities.create_entity();
+        entities.insert_component(first, 1_u32);
+        entities.insert_component(first, 1_isize);
+
+        // Insert in the opposite order, which should end up in the same
+        // archetype as the first entity.
+        let second = entities.create_entity();
+        entities.insert_component(second, 2_isize);
+        entities.insert_component(second, 2_u32);
 
-        assert_eq!(u32s.len(), 1);
-        assert_eq!(isizes.len(), 1);
-        assert!(u32s[0].is_none());
-        assert_eq!(*isizes[0].unwrap(), 2);
+        let location = entities.location(first).unwrap();
+        let archetype = &entities.archetypes[location.archetype];
+        assert_eq!(archetype.entities, vec![first, second]);
+        assert_eq!(*archetype.column::<u32>().unwrap(), vec![1, 2]);
+        assert_eq!(*archetype.column::<isize>().unwrap(), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_remove_component() {
+        let mut entities = Entities::new();
+        let entity = entities.create_entity();
+        entities.insert_component(entity, 1_u32);
+        entities.insert_component(entity, 2_isize);
+
+        entities.remove_component::<u32>(entity);
+
+        assert_eq!(entities.get_component::<u32>(entity), None);
+        assert_eq!(*entities.get_component::<isize>(entity).unwrap(), 2);
     }
 
     #[test]
     fn test_remove_entity() {
         let mut entities = Entities::new();
-        entities.register_component::<u32>();
-        entities.register_component::<isize>();
-        entities.create_entity();
-        entities.insert_component(1_u32);
-        entities.insert_component(2_isize);
+        let first = entities.create_entity();
+        entities.insert_component(first, 1_u32);
+        let second = entities.create_entity();
+        entities.insert_component(second, 2_u32);
+
+        entities.remove_entity(first);
 
-        entities.remove_entity(0);
+        assert!(!entities.is_alive(first));
+        assert_eq!(entities.get_component::<u32>(first), None);
+        // The second entity is swapped into the row of the first one
+        assert_eq!(*entities.get_component::<u32>(second).unwrap(), 2);
+        assert_eq!(*entities.archetypes[1].column::<u32>().unwrap(), vec![2]);
+    }
+
+    #[test]
+    fn test_remove_entity_recycles_slot() {
+        let mut entities = Entities::new();
+        let first = entities.create_entity();
+        entities.insert_component(first, 1_u32);
+        entities.remove_entity(first);
+
+        let second = entities.create_entity();
+        entities.insert_component(second, 2_u32);
+
+        assert_eq!(second.index, first.index);
+        assert_eq!(second.generation, first.generation + 1);
+        assert_eq!(entities.get_component::<u32>(first), None);
+        assert_eq!(*entities.get_component::<u32>(second).unwrap(), 2);
+    }
 
-        let u32s = entities.get_components::<u32>();
-        let isizes = entities.get_components::<isize>();
+    #[test]
+    fn test_stale_handle_is_ignored() {
+        let mut entities = Entities::new();
+        let first = entities.create_entity();
+        entities.remove_entity(first);
+        let second = entities.create_entity();
+        entities.insert_component(second, 2_u32);
+
+        entities.insert_component(first, 1_u32);
+        entities.remove_component::<u32>(first);
+        entities.remove_entity(first);
+
+        assert!(entities.is_alive(second));
+        assert_eq!(*entities.get_component::<u32>(second).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_create_entity_at() {
+        let mut entities = Entities::new();
+        let entity = Entity {
+            index: 2,
+            generation: 5,
+        };
+        assert!(entities.create_entity_at(entity));
+        assert!(!entities.create_entity_at(entity));
+        assert!(entities.is_alive(entity));
+        entities.rebuild_free_list();
 
-        assert_eq!(u32s.len(), 0);
-        assert_eq!(isizes.len(), 0);
+        // The slots before it are free to use
+        let first = entities.create_entity();
+        let second = entities.create_entity();
+        let third = entities.create_entity();
+        let mut indices = vec![first.index, second.index, third.index];
+        indices.sort();
+        assert_eq!(indices, vec![0, 1, 3]);
     }
 }
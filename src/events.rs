@@ -0,0 +1,156 @@
+/// A single event, along with its position in the stream of all events of the
+/// same type. Readers use the id to know which events they have already seen.
+struct EventInstance<T> {
+    id: usize,
+    event: T,
+}
+
+/// A channel of events of a single type, stored as a resource. Any system can
+/// send events, and any system can read them using an `EventReader`.
+///
+/// The events are double buffered: every frame the current events are moved
+/// to the previous buffer, and the previous ones are dropped. That means an
+/// event is available for the rest of the frame it was sent in, and the whole
+/// frame after that, so every system gets a chance to read it regardless of
+/// the order the systems run in.
+pub struct Events<T> {
+    previous: Vec<EventInstance<T>>,
+    current: Vec<EventInstance<T>>,
+    event_count: usize,
+}
+
+impl<T> Events<T> {
+    pub fn new() -> Self {
+        Events {
+            previous: vec![],
+            current: vec![],
+            event_count: 0,
+        }
+    }
+
+    /// Sends an event to every reader.
+    pub fn send(&mut self, event: T) {
+        self.current.push(EventInstance {
+            id: self.event_count,
+            event,
+        });
+        self.event_count += 1;
+    }
+
+    /// Drops the events from the previous frame, and moves the current ones
+    /// over to the previous buffer. Called once every frame by the world.
+    pub fn update(&mut self) {
+        self.previous = std::mem::take(&mut self.current);
+    }
+
+    /// Returns a reader that will only see the events sent after this call.
+    pub fn reader(&self) -> EventReader<T> {
+        EventReader {
+            last_read: self.event_count,
+            marker: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<T> Default for Events<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Keeps track of which events a system has already read. Each system that is
+/// interested in an event type should store its own reader.
+pub struct EventReader<T> {
+    last_read: usize,
+    marker: std::marker::PhantomData<T>,
+}
+
+impl<T> EventReader<T> {
+    pub fn new() -> Self {
+        EventReader {
+            last_read: 0,
+            marker: std::marker::PhantomData,
+        }
+    }
+
+    /// Returns all the events that this reader hasn't seen yet, oldest first.
+    pub fn read<'a>(&mut self, events: &'a Events<T>) -> Vec<&'a T> {
+        let last_read = self.last_read;
+        self.last_read = events.event_count;
+        events
+            .previous
+            .iter()
+            .chain(events.current.iter())
+            .filter(|instance| instance.id >= last_read)
+            .map(|instance| &instance.event)
+            .collect()
+    }
+}
+
+impl<T> Default for EventReader<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Explosion(usize);
+
+    #[test]
+    fn test_read() {
+        let mut events = Events::new();
+        let mut reader = EventReader::new();
+
+        events.send(Explosion(1));
+        events.send(Explosion(2));
+        assert_eq!(reader.read(&events), vec![&Explosion(1), &Explosion(2)]);
+        assert!(reader.read(&events).is_empty());
+
+        events.send(Explosion(3));
+        assert_eq!(reader.read(&events), vec![&Explosion(3)]);
+    }
+
+    #[test]
+    fn test_multiple_readers() {
+        let mut events = Events::new();
+        let mut first = EventReader::new();
+        let mut second = EventReader::new();
+
+        events.send(Explosion(1));
+        assert_eq!(first.read(&events), vec![&Explosion(1)]);
+
+        events.send(Explosion(2));
+        assert_eq!(first.read(&events), vec![&Explosion(2)]);
+        assert_eq!(second.read(&events), vec![&Explosion(1), &Explosion(2)]);
+    }
+
+    #[test]
+    fn test_events_survive_two_updates() {
+        let mut events = Events::new();
+        let mut reader = EventReader::new();
+
+        events.send(Explosion(1));
+        events.update();
+        events.send(Explosion(2));
+        assert_eq!(reader.read(&events), vec![&Explosion(1), &Explosion(2)]);
+
+        events.send(Explosion(3));
+        events.update();
+        events.update();
+        assert!(reader.read(&events).is_empty());
+    }
+
+    #[test]
+    fn test_reader_skips_old_events() {
+        let mut events = Events::new();
+        events.send(Explosion(1));
+
+        let mut reader = events.reader();
+        events.send(Explosion(2));
+        assert_eq!(reader.read(&events), vec![&Explosion(2)]);
+    }
+}
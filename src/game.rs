@@ -0,0 +1,260 @@
+extern crate console_error_panic_hook;
+extern crate wasm_bindgen;
+
+use crate::components::program::Program;
+use crate::replay::{self, apply_input, Recorder, Replay, ReplayInput};
+use crate::resources::canvas::{Canvas, WebCanvas};
+use crate::resources::input::KeyCode;
+use crate::resources::player::Player;
+use crate::resources::time::Time;
+use crate::scene::Scene;
+use crate::scenes::{create_scene, SceneRegistry};
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+pub struct Game {
+    scene: Scene,
+    scenes: SceneRegistry,
+    // The scene file the current scene was loaded from, used when restarting
+    scene_text: String,
+    // The timestamp of the previous frame, in milliseconds
+    last_timestamp: Option<f64>,
+    // Every input since the current scene was loaded, see `save_replay`
+    recorder: Recorder,
+}
+
+#[wasm_bindgen]
+impl Game {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Game {
+        console_error_panic_hook::set_once();
+        let scenes = SceneRegistry::built_in();
+        let name = scenes.names()[0];
+        let scene_text = scenes.get(name).unwrap().to_string();
+        let seed = random_seed();
+        let scene =
+            load_playable_scene(&scene_text, seed).expect("the built-in scenes should be valid");
+        let recorder = Recorder::new(&scene_text, seed);
+        Game {
+            scene,
+            scenes,
+            scene_text,
+            last_timestamp: None,
+            recorder,
+        }
+    }
+
+    /// Returns the names of the scenes that can be passed to `switch_scene`.
+    pub fn list_scenes(&self) -> JsValue {
+        serde_wasm_bindgen::to_value(&self.scenes.names()).unwrap()
+    }
+
+    /// Switches to the scene with the given name. The player's program is
+    /// kept. Returns an error message if there is no such scene.
+    pub fn switch_scene(&mut self, name: String) -> JsValue {
+        match self.scenes.get(&name) {
+            Some(text) => {
+                let text = text.to_string();
+                self.load_scene(text)
+            }
+            None => JsValue::from_str(&format!("there is no scene named `{}`", name)),
+        }
+    }
+
+    /// Starts the current scene over from the beginning, keeping the player's
+    /// program.
+    pub fn restart(&mut self) {
+        self.replace_scene(self.scene_text.clone()).unwrap();
+    }
+
+    pub fn change_program(&mut self, input: String) -> JsValue {
+        self.input(ReplayInput::ChangeProgram { source: input })
+            .unwrap();
+
+        let player = self.scene.world.get_resource::<Player>().unwrap().entity;
+        let program = self.scene.world.get::<Program>(player).unwrap();
+        if let Err(errors) = &program.program {
+            serde_wasm_bindgen::to_value(&errors).unwrap()
+        } else {
+            JsValue::null()
+        }
+    }
+
+    /// Runs a single frame. The timestamp is the one passed to
+    /// `requestAnimationFrame`, in milliseconds, which is used to decide how
+    /// many simulation steps to run.
+    pub fn tick(&mut self, timestamp: f64) -> JsValue {
+        let frame_time = match self.last_timestamp {
+            Some(last_timestamp) => ((timestamp - last_timestamp) / 1000.0) as f32,
+            None => 0.0,
+        };
+        self.last_timestamp = Some(timestamp);
+        self.scene.update(frame_time);
+
+        let player = self.scene.world.get_resource::<Player>().unwrap().entity;
+        let program = self.scene.world.get::<Program>(player).unwrap();
+        if let Some(error) = &program.error {
+            serde_wasm_bindgen::to_value(&error).unwrap()
+        } else {
+            JsValue::null()
+        }
+    }
+
+    /// Returns the lines the player's program has written with `LOG`, oldest
+    /// first.
+    pub fn console_output(&self) -> JsValue {
+        let player = self.scene.world.get_resource::<Player>().unwrap().entity;
+        let program = self.scene.world.get::<Program>(player).unwrap();
+        serde_wasm_bindgen::to_value(&program.console.lines()).unwrap()
+    }
+
+    /// Pauses or resumes the simulation. The scene is still rendered while
+    /// paused.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.input(ReplayInput::SetPaused { paused }).unwrap();
+    }
+
+    /// Changes how fast the simulation runs compared to real time, e.g. 0.5
+    /// for half speed or 10.0 to fast-forward. Returns an error message if the
+    /// scale is negative or not a number.
+    pub fn set_time_scale(&mut self, scale: f32) -> JsValue {
+        match self.input(ReplayInput::SetTimeScale { scale }) {
+            Ok(()) => JsValue::null(),
+            Err(error) => JsValue::from_str(&error),
+        }
+    }
+
+    /// Replaces the current scene with one described by a scene file. Returns
+    /// an error message if the scene couldn't be loaded, in which case the
+    /// current scene keeps running.
+    pub fn load_scene(&mut self, text: String) -> JsValue {
+        match self.replace_scene(text) {
+            Ok(()) => JsValue::null(),
+            Err(error) => JsValue::from_str(&error),
+        }
+    }
+
+    /// Serializes the current state of the scene, so that it can be stored
+    /// and loaded again later.
+    pub fn save_state(&self) -> String {
+        self.scene.world.snapshot()
+    }
+
+    /// Restores a state returned by `save_state`. Returns an error message if
+    /// the state couldn't be loaded, in which case the scene is unchanged.
+    pub fn load_state(&mut self, state: String) -> JsValue {
+        match self.input(ReplayInput::LoadState { state }) {
+            Ok(()) => JsValue::null(),
+            Err(error) => JsValue::from_str(&error),
+        }
+    }
+
+    /// Returns a replay of everything that has happened since the current
+    /// scene was loaded, which can be passed to `load_replay` to reproduce
+    /// the exact same run.
+    pub fn save_replay(&self) -> String {
+        self.recorder.finish(&self.scene.world).to_json()
+    }
+
+    /// Replays a run saved with `save_replay`, step by step from the start of
+    /// its scene, and continues from where the recording ended. Returns an
+    /// error message if the replay couldn't be loaded, in which case the
+    /// current scene keeps running, or if it didn't end up in the same state
+    /// as the recording.
+    pub fn load_replay(&mut self, text: String) -> JsValue {
+        let replay = match Replay::from_json(&text) {
+            Ok(replay) => replay,
+            Err(error) => return JsValue::from_str(&error),
+        };
+        let mut world = match replay.load_world() {
+            Ok(world) => world,
+            Err(error) => return JsValue::from_str(&error),
+        };
+        world.create_resource(Canvas::new(WebCanvas::new()));
+        let mut scene = create_scene(world);
+        let result = replay.play(&mut scene);
+
+        self.scene = scene;
+        self.scene_text = replay.scene.clone();
+        self.recorder = replay.into_recorder();
+        match result {
+            Ok(()) => JsValue::null(),
+            Err(error) => JsValue::from_str(&error),
+        }
+    }
+
+    pub fn keydown(&mut self, key: String) {
+        if KeyCode::new(&key).is_some() {
+            self.input(ReplayInput::KeyDown { key }).unwrap();
+        }
+    }
+
+    pub fn keyup(&mut self, key: String) {
+        if KeyCode::new(&key).is_some() {
+            self.input(ReplayInput::KeyUp { key }).unwrap();
+        }
+    }
+}
+
+impl Game {
+    // Applies an input to the current scene, and records it so that it's part
+    // of the replay.
+    fn input(&mut self, input: ReplayInput) -> Result<(), String> {
+        apply_input(&mut self.scene.world, &input)?;
+        self.recorder.record(&self.scene.world, input);
+        Ok(())
+    }
+
+    // Replaces the current scene with one loaded from a scene file, carrying
+    // over the player's program and the speed of the simulation. This starts a
+    // new recording, where the carried over settings are the first inputs.
+    fn replace_scene(&mut self, text: String) -> Result<(), String> {
+        let seed = random_seed();
+        let scene = load_playable_scene(&text, seed)?;
+
+        let source = {
+            let player = self.scene.world.get_resource::<Player>().unwrap().entity;
+            self.scene
+                .world
+                .get::<Program>(player)
+                .unwrap()
+                .source
+                .clone()
+        };
+        let (scale, paused) = {
+            let time = self.scene.world.get_resource::<Time>().unwrap();
+            (time.scale(), time.paused)
+        };
+
+        self.scene = scene;
+        self.recorder = Recorder::new(&text, seed);
+        self.scene_text = text;
+
+        if !source.is_empty() {
+            self.input(ReplayInput::ChangeProgram { source })?;
+        }
+        self.input(ReplayInput::SetTimeScale { scale })?;
+        self.input(ReplayInput::SetPaused { paused })?;
+        Ok(())
+    }
+}
+
+// Loads a scene that can be controlled by the player. The scene draws to the
+// canvas on the page.
+fn load_playable_scene(text: &str, seed: u64) -> Result<Scene, String> {
+    let mut world = replay::load_world(text, seed)?;
+    world.create_resource(Canvas::new(WebCanvas::new()));
+    Ok(create_scene(world))
+}
+
+// A new seed for every scene that is loaded. Runs only need to be the same
+// when they are replayed, and the replay keeps track of the seed.
+fn random_seed() -> u64 {
+    (js_sys::Math::random() * (1u64 << 53) as f64) as u64
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new()
+    }
+}
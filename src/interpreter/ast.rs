@@ -1,8 +1,17 @@
-use crate::interpreter::token::Token;
+use crate::interpreter::token::{Span, Token};
 use std::fmt;
 
+// Every statement and expression knows where it is in the source code, so that
+// errors can point to it. Two nodes are equal if they are made up of the same
+// code, no matter where it is.
+#[derive(Debug, Clone)]
+pub struct Statement {
+    pub kind: StatementKind,
+    pub span: Span,
+}
+
 #[derive(Debug, PartialEq, Clone)]
-pub enum Statement {
+pub enum StatementKind {
     Let {
         identifier: String,
         expression: Expression,
@@ -10,6 +19,12 @@ pub enum Statement {
     Return {
         expression: Expression,
     },
+    // A variable that keeps its value between runs of the program. The
+    // expression is only evaluated the first time the statement runs.
+    Static {
+        identifier: String,
+        expression: Expression,
+    },
     // Expression statements is a statement that consists solely of one
     // expression. It used to handle cases where we write expression in the
     // top-level code. E.g the second line here:
@@ -19,6 +34,19 @@ pub enum Statement {
     Expression {
         expression: Expression,
     },
+    While {
+        condition: Expression,
+        body: BlockStatement,
+    },
+    // Loops over the integers from `start` up to, but not including, `end`
+    For {
+        identifier: String,
+        start: Expression,
+        end: Expression,
+        body: BlockStatement,
+    },
+    Break,
+    Continue,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -26,12 +54,19 @@ pub struct BlockStatement {
     pub statements: Vec<Statement>,
 }
 
+#[derive(Debug, Clone)]
+pub struct Expression {
+    pub kind: ExpressionKind,
+    pub span: Span,
+}
+
 #[derive(Debug, PartialEq, Clone)]
-pub enum Expression {
+pub enum ExpressionKind {
     Identifier(String),
     Int(isize),
     Float(f64),
     Boolean(bool),
+    String(String),
     // Need to use a Box here to avoid an infinitely large size on the
     // Expression type. Using Box means we just store a pointer to the
     // associated expression.
@@ -59,6 +94,43 @@ pub enum Expression {
     },
 }
 
+impl Statement {
+    pub fn new(kind: StatementKind, span: Span) -> Statement {
+        Statement { kind, span }
+    }
+}
+
+impl Expression {
+    pub fn new(kind: ExpressionKind, span: Span) -> Expression {
+        Expression { kind, span }
+    }
+}
+
+impl PartialEq for Statement {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+    }
+}
+
+impl PartialEq for Expression {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+    }
+}
+
+// Mostly useful in tests, where the position of a node doesn't matter
+impl From<StatementKind> for Statement {
+    fn from(kind: StatementKind) -> Self {
+        Statement::new(kind, Span::default())
+    }
+}
+
+impl From<ExpressionKind> for Expression {
+    fn from(kind: ExpressionKind) -> Self {
+        Expression::new(kind, Span::default())
+    }
+}
+
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub enum Operator {
     Plus,
@@ -112,19 +184,26 @@ impl fmt::Display for Program {
 }
 
 impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)
+    }
+}
+
+impl fmt::Display for ExpressionKind {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Expression::Identifier(name) => write!(f, "{}", name),
-            Expression::Int(literal) => write!(f, "{}", literal),
-            Expression::Float(literal) => write!(f, "{}", literal),
-            Expression::Boolean(boolean) => write!(f, "{}", boolean),
-            Expression::Prefix { operator, right } => write!(f, "({}{})", operator, right),
-            Expression::Infix {
+            ExpressionKind::Identifier(name) => write!(f, "{}", name),
+            ExpressionKind::Int(literal) => write!(f, "{}", literal),
+            ExpressionKind::Float(literal) => write!(f, "{}", literal),
+            ExpressionKind::Boolean(boolean) => write!(f, "{}", boolean),
+            ExpressionKind::String(string) => write!(f, "{:?}", string),
+            ExpressionKind::Prefix { operator, right } => write!(f, "({}{})", operator, right),
+            ExpressionKind::Infix {
                 operator,
                 left,
                 right,
             } => write!(f, "({} {} {})", left, operator, right),
-            Expression::If {
+            ExpressionKind::If {
                 condition,
                 consequence,
                 alternative,
@@ -137,10 +216,10 @@ impl fmt::Display for Expression {
 
                 Ok(())
             }
-            Expression::Function { parameters, body } => {
+            ExpressionKind::Function { parameters, body } => {
                 write!(f, "({}) {}", parameters.join(","), body)
             }
-            Expression::Call {
+            ExpressionKind::Call {
                 function,
                 arguments,
             } => {
@@ -161,13 +240,30 @@ impl fmt::Display for Expression {
 
 impl fmt::Display for Statement {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Statement::Let {
+        match &self.kind {
+            StatementKind::Let {
                 identifier,
                 expression,
             } => write!(f, "let {} = {};", identifier, expression),
-            Statement::Return { expression } => write!(f, "return {};", expression),
-            Statement::Expression { expression } => write!(f, "{}", expression),
+            StatementKind::Return { expression } => write!(f, "return {};", expression),
+            StatementKind::Static {
+                identifier,
+                expression,
+            } => write!(f, "static let {} = {};", identifier, expression),
+            StatementKind::Expression { expression } => write!(f, "{}", expression),
+            StatementKind::While { condition, body } => write!(f, "while {} {}", condition, body),
+            StatementKind::For {
+                identifier,
+                start,
+                end,
+                body,
+            } => write!(
+                f,
+                "for {} in range({}, {}) {}",
+                identifier, start, end, body
+            ),
+            StatementKind::Break => write!(f, "break;"),
+            StatementKind::Continue => write!(f, "continue;"),
         }
     }
 }
@@ -1,26 +1,60 @@
 use crate::interpreter::ast::BlockStatement;
 use crate::interpreter::ast::Expression;
+use crate::interpreter::ast::ExpressionKind;
 use crate::interpreter::ast::Operator;
 use crate::interpreter::ast::Program;
 use crate::interpreter::ast::Statement;
+use crate::interpreter::ast::StatementKind;
 use crate::interpreter::object::Command;
 use crate::interpreter::object::Environment;
 use crate::interpreter::object::Object;
 use crate::interpreter::object::RuntimeError;
+use crate::interpreter::token::Span;
+
+/// The number of CPU cycles a program can use each time it's evaluated, unless
+/// it's given another budget. Every statement, expression and loop iteration
+/// uses one cycle, so that a program that never ends can't freeze the game.
+pub const DEFAULT_CPU_CYCLES: usize = 10_000;
+
+/// How deep function calls can be nested before the program stops, so that
+/// runaway recursion can't overflow the stack of the game itself.
+pub const MAX_CALL_DEPTH: usize = 64;
+
+/// The longest string, in bytes, a program can create. Strings can double in
+/// length with every addition, so they would otherwise use up all memory long
+/// before the program runs out of cycles.
+pub const MAX_STRING_LENGTH: usize = 10_000;
 
 pub struct Evaluator {
     pub commands: Vec<Command>,
+    /// The number of cycles used so far. Programs can read it as `CYCLES`.
+    pub cycles: usize,
+    /// The number of cycles the program can use. Programs can read it as
+    /// `MAX_CYCLES`.
+    pub max_cycles: usize,
+    depth: usize,
 }
 
 impl Evaluator {
     pub fn new() -> Evaluator {
-        Evaluator { commands: vec![] }
+        Evaluator::with_cycles(DEFAULT_CPU_CYCLES)
+    }
+
+    pub fn with_cycles(max_cycles: usize) -> Evaluator {
+        Evaluator {
+            commands: vec![],
+            cycles: 0,
+            max_cycles,
+            depth: 0,
+        }
     }
 
     pub fn eval(&mut self, program: &Program, environment: &mut Environment) -> Object {
         // Reset commands each time eval is called so we don't keep commands
         // from previous executions.
         self.commands = vec![];
+        self.cycles = 0;
+        self.depth = 0;
         let mut result = Object::Null;
         for statement in &program.statements {
             result = self.eval_statement(statement, environment);
@@ -34,16 +68,21 @@ impl Evaluator {
     }
 
     fn eval_statement(&mut self, statement: &Statement, environment: &mut Environment) -> Object {
-        match statement {
-            Statement::Return { expression } => {
+        if let Err(error) = self.use_cycle(statement.span) {
+            return Object::Error(error);
+        }
+        match &statement.kind {
+            StatementKind::Return { expression } => {
                 let return_value = self.eval_expression(expression, environment);
                 if let Object::Error(_) = return_value {
                     return return_value;
                 }
                 Object::Return(Box::new(return_value))
             }
-            Statement::Expression { expression } => self.eval_expression(expression, environment),
-            Statement::Let {
+            StatementKind::Expression { expression } => {
+                self.eval_expression(expression, environment)
+            }
+            StatementKind::Let {
                 identifier,
                 expression,
             } => {
@@ -54,26 +93,140 @@ impl Evaluator {
                 environment.set(identifier.clone(), object.clone());
                 object
             }
+            StatementKind::Static {
+                identifier,
+                expression,
+            } => {
+                // Only the first run initializes the variable. After that it
+                // keeps the value it had at the end of the previous run.
+                if environment.is_static(identifier) {
+                    if let Some(object) = environment.get(identifier) {
+                        return object;
+                    }
+                }
+                let object = self.eval_expression(expression, environment);
+                if let Object::Error(_) = object {
+                    return object;
+                }
+                environment.set_static(identifier.clone(), object.clone());
+                object
+            }
+            StatementKind::While { condition, body } => {
+                self.eval_while_statement(condition, body, statement.span, environment)
+            }
+            StatementKind::For {
+                identifier,
+                start,
+                end,
+                body,
+            } => self.eval_for_statement(identifier, start, end, body, statement.span, environment),
+            StatementKind::Break => Object::Break,
+            StatementKind::Continue => Object::Continue,
+        }
+    }
+
+    fn eval_while_statement(
+        &mut self,
+        condition: &Expression,
+        body: &BlockStatement,
+        span: Span,
+        environment: &mut Environment,
+    ) -> Object {
+        loop {
+            if let Err(error) = self.use_cycle(span) {
+                return Object::Error(error);
+            }
+            let condition = self.eval_expression(condition, environment);
+            if let Object::Error(_) = condition {
+                return condition;
+            }
+            if !self.is_truthy(condition) {
+                return Object::Null;
+            }
+            match self.eval_block_statement(body, environment) {
+                Object::Break => return Object::Null,
+                object @ (Object::Return(_) | Object::Error(_)) => return object,
+                _ => (),
+            }
         }
     }
 
+    fn eval_for_statement(
+        &mut self,
+        identifier: &str,
+        start: &Expression,
+        end: &Expression,
+        body: &BlockStatement,
+        span: Span,
+        environment: &mut Environment,
+    ) -> Object {
+        let range_start = self.eval_expression(start, environment);
+        if let Object::Error(_) = range_start {
+            return range_start;
+        }
+        let range_end = self.eval_expression(end, environment);
+        if let Object::Error(_) = range_end {
+            return range_end;
+        }
+        let (range_start, range_end) = match (range_start, range_end) {
+            (Object::Integer(range_start), Object::Integer(range_end)) => (range_start, range_end),
+            (range_start, range_end) => {
+                let message = format!(
+                    "RANGE expects integers, got {} and {}",
+                    range_start.name(),
+                    range_end.name()
+                );
+                let error = RuntimeError::at(message, start.span.to(end.span));
+                return Object::Error(error);
+            }
+        };
+
+        for value in range_start..range_end {
+            if let Err(error) = self.use_cycle(span) {
+                return Object::Error(error);
+            }
+            environment.set(identifier.to_string(), Object::Integer(value));
+            match self.eval_block_statement(body, environment) {
+                Object::Break => break,
+                object @ (Object::Return(_) | Object::Error(_)) => return object,
+                _ => (),
+            }
+        }
+        Object::Null
+    }
+
+    // Uses one CPU cycle. Returns an error at the given span once the program
+    // has run out of cycles.
+    fn use_cycle(&mut self, span: Span) -> Result<(), RuntimeError> {
+        self.cycles += 1;
+        if self.cycles > self.max_cycles {
+            let message = String::from("out of CPU cycles");
+            return Err(RuntimeError::at(message, span));
+        }
+        Ok(())
+    }
+
     fn eval_expression(
         &mut self,
         expression: &Expression,
         environment: &mut Environment,
     ) -> Object {
-        match expression {
-            Expression::Int(value) => Object::Integer(*value),
-            Expression::Float(value) => Object::Float(*value),
-            Expression::Boolean(value) => Object::Boolean(*value),
-            Expression::Prefix { operator, right } => {
+        if let Err(error) = self.use_cycle(expression.span) {
+            return Object::Error(error);
+        }
+        let object = match &expression.kind {
+            ExpressionKind::Int(value) => Object::Integer(*value),
+            ExpressionKind::Float(value) => Object::Float(*value),
+            ExpressionKind::Boolean(value) => Object::Boolean(*value),
+            ExpressionKind::String(value) => Object::String(value.clone()),
+            ExpressionKind::Prefix { operator, right } => {
                 let object = self.eval_expression(right, environment);
                 if let Object::Error(_) = object {
                     return object;
                 }
                 self.eval_prefix_expression(*operator, object)
             }
-            Expression::Infix {
+            ExpressionKind::Infix {
                 operator,
                 left,
                 right,
@@ -88,7 +241,7 @@ impl Evaluator {
                 }
                 self.eval_infix_expression(*operator, left, right)
             }
-            Expression::If {
+            ExpressionKind::If {
                 condition,
                 consequence,
                 alternative,
@@ -99,13 +252,13 @@ impl Evaluator {
                 }
                 self.eval_if_expression(condition, consequence, alternative, environment)
             }
-            Expression::Identifier(name) => self.eval_identifier(name.clone(), environment),
-            Expression::Function { parameters, body } => Object::Function {
+            ExpressionKind::Identifier(name) => self.eval_identifier(name.clone(), environment),
+            ExpressionKind::Function { parameters, body } => Object::Function {
                 parameters: parameters.clone(),
                 body: body.clone(),
                 environment: environment.clone(),
             },
-            Expression::Call {
+            ExpressionKind::Call {
                 function,
                 arguments,
             } => {
@@ -119,9 +272,26 @@ impl Evaluator {
                         body,
                         environment,
                     } => {
+                        if self.depth >= MAX_CALL_DEPTH {
+                            let message = format!("stack overflow at depth {}", self.depth);
+                            return Object::Error(RuntimeError::at(message, expression.span));
+                        }
+                        if let [Object::Error(_)] = arguments.as_slice() {
+                            return arguments.into_iter().next().unwrap();
+                        }
+                        if arguments.len() != parameters.len() {
+                            let message = format!(
+                                "wrong number of arguments. got={}, want={}",
+                                arguments.len(),
+                                parameters.len()
+                            );
+                            return Object::Error(RuntimeError::at(message, expression.span));
+                        }
                         let mut extended_environment =
                             self.extend_function_environment(environment, parameters, arguments);
+                        self.depth += 1;
                         let evaluated = self.eval_block_statement(&body, &mut extended_environment);
+                        self.depth -= 1;
                         match evaluated {
                             Object::Return(return_value) => *return_value,
                             _ => evaluated,
@@ -141,6 +311,16 @@ impl Evaluator {
                     _ => Object::Null,
                 }
             }
+        };
+
+        // Errors point to the innermost expression they came from, so only
+        // errors that don't know where they are yet get this span.
+        match object {
+            Object::Error(RuntimeError {
+                message,
+                span: None,
+            }) => Object::Error(RuntimeError::at(message, expression.span)),
+            _ => object,
         }
     }
 
@@ -151,9 +331,8 @@ impl Evaluator {
         arguments: Vec<Object>,
     ) -> Environment {
         let mut env = Environment::extend(environment);
-        for (index, argument) in arguments.iter().enumerate() {
-            let param = parameters[index].clone();
-            env.set(param, argument.clone());
+        for (param, argument) in parameters.into_iter().zip(arguments) {
+            env.set(param, argument);
         }
         env
     }
@@ -177,6 +356,8 @@ impl Evaluator {
     fn eval_identifier(&mut self, name: String, environment: &mut Environment) -> Object {
         match environment.get(&name) {
             Some(value) => value.clone(),
+            None if name == "CYCLES" => Object::Integer(self.cycles as isize),
+            None if name == "MAX_CYCLES" => Object::Integer(self.max_cycles as isize),
             None => {
                 let message = format!("identifier not found: {}", name);
                 let error = RuntimeError::new(message);
@@ -208,7 +389,10 @@ impl Evaluator {
 
     fn eval_minus_prefix_operator(&mut self, object: Object) -> Object {
         match object {
-            Object::Integer(value) => Object::Integer(-value),
+            Object::Integer(value) => match value.checked_neg() {
+                Some(value) => Object::Integer(value),
+                None => Object::Error(RuntimeError::new(String::from("integer overflow"))),
+            },
             Object::Float(value) => Object::Float(-value),
             _ => {
                 let message = format!("unknown operator: -{}", object.name());
@@ -232,6 +416,9 @@ impl Evaluator {
             (Object::Float(left), Object::Float(right)) => {
                 self.eval_float_infix_expression(operator, left, right)
             }
+            (Object::String(left), Object::String(right)) => {
+                self.eval_string_infix_expression(operator, left, right)
+            }
             _ => {
                 let message = format!(
                     "type mismatch: {} {} {}",
@@ -249,11 +436,29 @@ impl Evaluator {
         left: isize,
         right: isize,
     ) -> Object {
+        // Integers can't be allowed to overflow or be divided by zero, since
+        // that would panic.
+        let checked = match operator {
+            Operator::Plus => Some(left.checked_add(right)),
+            Operator::Minus => Some(left.checked_sub(right)),
+            Operator::Divide if right == 0 => {
+                let error = RuntimeError::new(String::from("division by zero"));
+                return Object::Error(error);
+            }
+            Operator::Divide => Some(left.checked_div(right)),
+            Operator::Multiply => Some(left.checked_mul(right)),
+            _ => None,
+        };
+        match checked {
+            Some(Some(value)) => return Object::Integer(value),
+            Some(None) => {
+                let error = RuntimeError::new(String::from("integer overflow"));
+                return Object::Error(error);
+            }
+            None => {}
+        }
+
         match operator {
-            Operator::Plus => Object::Integer(left + right),
-            Operator::Minus => Object::Integer(left - right),
-            Operator::Divide => Object::Integer(left / right),
-            Operator::Multiply => Object::Integer(left * right),
             Operator::LessThan => Object::Boolean(left < right),
             Operator::GreaterThan => Object::Boolean(left > right),
             Operator::Equal => Object::Boolean(left == right),
@@ -284,6 +489,30 @@ impl Evaluator {
         }
     }
 
+    fn eval_string_infix_expression(
+        &mut self,
+        operator: Operator,
+        left: String,
+        right: String,
+    ) -> Object {
+        match operator {
+            Operator::Plus if left.len() + right.len() > MAX_STRING_LENGTH => {
+                let message = format!("string is longer than {} bytes", MAX_STRING_LENGTH);
+                Object::Error(RuntimeError::new(message))
+            }
+            Operator::Plus => Object::String(left + &right),
+            Operator::LessThan => Object::Boolean(left < right),
+            Operator::GreaterThan => Object::Boolean(left > right),
+            Operator::Equal => Object::Boolean(left == right),
+            Operator::NotEqual => Object::Boolean(left != right),
+            _ => {
+                let message = format!("unknown operator: string {} string", operator);
+                let error = RuntimeError::new(message);
+                Object::Error(error)
+            }
+        }
+    }
+
     fn eval_boolean_infix_expression(
         &mut self,
         operator: Operator,
@@ -309,7 +538,7 @@ impl Evaluator {
         for statement in &statement.statements {
             object = self.eval_statement(statement, environment);
             match object {
-                Object::Return(_) | Object::Error(_) => {
+                Object::Return(_) | Object::Error(_) | Object::Break | Object::Continue => {
                     return object;
                 }
                 _ => (),
@@ -347,6 +576,7 @@ mod tests {
     use super::*;
     use crate::interpreter::lexer::Lexer;
     use crate::interpreter::parser::Parser;
+    use crate::interpreter::token::Span;
 
     #[test]
     fn test_eval_integer_expression() {
@@ -494,44 +724,29 @@ mod tests {
         let tests = vec![
             (
                 "5 + TRUE",
-                Object::Error(RuntimeError::new(String::from(
-                    "type mismatch: integer + boolean",
-                ))),
+                error("type mismatch: integer + boolean", 1, 1, 8),
             ),
             (
                 "
                 5 + TRUE
                 5
                 ",
-                Object::Error(RuntimeError::new(String::from(
-                    "type mismatch: integer + boolean",
-                ))),
-            ),
-            (
-                "-TRUE",
-                Object::Error(RuntimeError::new(String::from(
-                    "unknown operator: -boolean",
-                ))),
+                error("type mismatch: integer + boolean", 2, 17, 8),
             ),
+            ("-TRUE", error("unknown operator: -boolean", 1, 1, 5)),
             (
                 "TRUE + FALSE",
-                Object::Error(RuntimeError::new(String::from(
-                    "unknown operator: boolean + boolean",
-                ))),
+                error("unknown operator: boolean + boolean", 1, 1, 12),
             ),
             (
                 "5
                 TRUE + FALSE
                 5",
-                Object::Error(RuntimeError::new(String::from(
-                    "unknown operator: boolean + boolean",
-                ))),
+                error("unknown operator: boolean + boolean", 2, 17, 12),
             ),
             (
                 "IF 10 > 1 DO TRUE + FALSE END",
-                Object::Error(RuntimeError::new(String::from(
-                    "unknown operator: boolean + boolean",
-                ))),
+                error("unknown operator: boolean + boolean", 1, 14, 12),
             ),
             (
                 "
@@ -542,21 +757,30 @@ mod tests {
                     RETURN 1
                 END
                 ",
-                Object::Error(RuntimeError::new(String::from(
-                    "unknown operator: boolean + boolean",
-                ))),
+                error("unknown operator: boolean + boolean", 4, 32, 12),
+            ),
+            ("foobar", error("identifier not found: foobar", 1, 1, 6)),
+            ("1 / 0", error("division by zero", 1, 1, 5)),
+            (
+                "9223372036854775807 + 1",
+                error("integer overflow", 1, 1, 23),
+            ),
+            (
+                "4611686018427387904 * 2",
+                error("integer overflow", 1, 1, 23),
             ),
             (
-                "foobar",
-                Object::Error(RuntimeError::new(String::from(
-                    "identifier not found: foobar",
-                ))),
+                "
+                LET IDENTITY = FUNC X DO X END
+                IDENTITY(1, 2)
+                ",
+                error("wrong number of arguments. got=2, want=1", 3, 17, 14),
             ),
         ];
 
         for (input, expected_output) in tests {
             let object = test_eval(input);
-            assert_eq!(object, expected_output);
+            assert_eq!(without_offset(object), expected_output);
         }
     }
 
@@ -598,13 +822,15 @@ mod tests {
             Object::Function {
                 parameters: vec![String::from("X")],
                 body: BlockStatement {
-                    statements: vec![Statement::Expression {
-                        expression: Expression::Infix {
+                    statements: vec![StatementKind::Expression {
+                        expression: ExpressionKind::Infix {
                             operator: Operator::Plus,
-                            left: Box::new(Expression::Identifier(String::from("X"))),
-                            right: Box::new(Expression::Int(2)),
+                            left: Box::new(ExpressionKind::Identifier(String::from("X")).into()),
+                            right: Box::new(ExpressionKind::Int(2).into()),
                         }
-                    }]
+                        .into()
+                    }
+                    .into()]
                 },
                 environment: Environment::new()
             }
@@ -696,16 +922,12 @@ mod tests {
             (
                 "SET_THRUST(TRUE)",
                 vec![],
-                Object::Error(RuntimeError::new(String::from(
-                    "argument not supported, got boolean",
-                ))),
+                error("argument not supported, got boolean", 1, 1, 16),
             ),
             (
                 "SET_THRUST(0, 1)",
                 vec![],
-                Object::Error(RuntimeError::new(String::from(
-                    "wrong number of arguments. got=2, want=1",
-                ))),
+                error("wrong number of arguments. got=2, want=1", 1, 1, 16),
             ),
         ];
 
@@ -739,11 +961,314 @@ mod tests {
                 },
             );
             let object = evaluator.eval(&program, &mut environment);
-            assert_eq!(object, expected_output);
+            assert_eq!(without_offset(object), expected_output);
             assert_eq!(evaluator.commands, expected_commands);
         }
     }
 
+    #[test]
+    fn test_while_statements() {
+        let tests = vec![
+            (
+                "
+                LET X = 0
+                WHILE X < 10 DO
+                    LET X = X + 1
+                END
+                X
+                ",
+                Object::Integer(10),
+            ),
+            ("WHILE FALSE DO 1 END", Object::Null),
+            (
+                "
+                LET X = 0
+                WHILE TRUE DO
+                    LET X = X + 1
+                    IF X > 4 DO BREAK END
+                END
+                X
+                ",
+                Object::Integer(5),
+            ),
+        ];
+
+        for (input, expected_output) in tests {
+            let object = test_eval(input);
+            assert_eq!(object, expected_output);
+        }
+    }
+
+    #[test]
+    fn test_for_statements() {
+        let tests = vec![
+            (
+                "
+                LET SUM = 0
+                FOR I IN RANGE(0, 5) DO
+                    LET SUM = SUM + I
+                END
+                SUM
+                ",
+                Object::Integer(10),
+            ),
+            (
+                "
+                LET SUM = 0
+                FOR I IN RANGE(5, 0) DO
+                    LET SUM = SUM + I
+                END
+                SUM
+                ",
+                Object::Integer(0),
+            ),
+            (
+                "
+                LET SUM = 0
+                FOR I IN RANGE(0, 10) DO
+                    IF I == 2 DO CONTINUE END
+                    IF I == 5 DO BREAK END
+                    LET SUM = SUM + I
+                END
+                SUM
+                ",
+                Object::Integer(8),
+            ),
+            (
+                "
+                LET COUNT = 0
+                FOR I IN RANGE(0, 3) DO
+                    FOR J IN RANGE(0, 3) DO
+                        IF J > I DO BREAK END
+                        LET COUNT = COUNT + 1
+                    END
+                END
+                COUNT
+                ",
+                Object::Integer(6),
+            ),
+            (
+                "
+                LET FIND = FUNC N DO
+                    FOR I IN RANGE(0, 100) DO
+                        IF I * I > N DO RETURN I END
+                    END
+                    RETURN -1
+                END
+                FIND(50)
+                ",
+                Object::Integer(8),
+            ),
+        ];
+
+        for (input, expected_output) in tests {
+            let object = test_eval(input);
+            assert_eq!(object, expected_output);
+        }
+    }
+
+    #[test]
+    fn test_loop_errors() {
+        let tests = vec![
+            (
+                "FOR I IN RANGE(0, 1.5) DO END",
+                error("RANGE expects integers, got integer and float", 1, 16, 6),
+            ),
+            ("WHILE TRUE DO END", error("out of CPU cycles", 1, 7, 4)),
+            (
+                "
+                LET X = 0
+                WHILE TRUE DO
+                    LET X = X + 1
+                END
+                ",
+                error("out of CPU cycles", 3, 23, 4),
+            ),
+        ];
+
+        for (input, expected_output) in tests {
+            let object = test_eval(input);
+            assert_eq!(without_offset(object), expected_output);
+        }
+    }
+
+    #[test]
+    fn test_cpu_cycles() {
+        let tests = vec![
+            // The statement and the expression
+            ("CYCLES", 10, Object::Integer(2)),
+            ("MAX_CYCLES", 10, Object::Integer(10)),
+            ("LET A = 1 + 2\nCYCLES", 10, Object::Integer(6)),
+            (
+                "LET A = 1 + 2\nCYCLES",
+                5,
+                error("out of CPU cycles", 2, 1, 6),
+            ),
+            (
+                "
+                LET COUNT = 0
+                WHILE CYCLES < MAX_CYCLES - 20 DO
+                    LET COUNT = COUNT + 1
+                END
+                COUNT
+                ",
+                100,
+                Object::Integer(8),
+            ),
+        ];
+
+        for (input, max_cycles, expected_output) in tests {
+            let lexer = Lexer::new(input);
+            let mut parser = Parser::new(lexer);
+            let program = parser.parse_program().unwrap();
+            let mut environment = Environment::new();
+            let mut evaluator = Evaluator::with_cycles(max_cycles);
+
+            let object = evaluator.eval(&program, &mut environment);
+            assert_eq!(without_offset(object), expected_output);
+        }
+    }
+
+    #[test]
+    fn test_call_depth() {
+        let tests = vec![
+            (
+                "
+                LET COUNT = FUNC F N DO
+                    IF N == 0 DO RETURN 0 END
+                    RETURN F(F, N - 1) + 1
+                END
+                COUNT(COUNT, 60)
+                ",
+                Object::Integer(60),
+            ),
+            (
+                "
+                LET COUNT = FUNC F N DO
+                    IF N == 0 DO RETURN 0 END
+                    RETURN F(F, N - 1) + 1
+                END
+                COUNT(COUNT, 100)
+                ",
+                error("stack overflow at depth 64", 4, 28, 11),
+            ),
+            (
+                "
+                LET LOOP = FUNC F DO F(F) END
+                LOOP(LOOP)
+                ",
+                error("stack overflow at depth 64", 2, 38, 4),
+            ),
+        ];
+
+        for (input, expected_output) in tests {
+            let object = test_eval(input);
+            assert_eq!(without_offset(object), expected_output);
+        }
+    }
+
+    #[test]
+    fn test_strings() {
+        let tests = vec![
+            (r#""HELLO""#, Object::String(String::from("HELLO"))),
+            (
+                r#""ALTITUDE: " + "100""#,
+                Object::String(String::from("ALTITUDE: 100")),
+            ),
+            (r#""A" == "A""#, Object::Boolean(true)),
+            (r#""A" != "A""#, Object::Boolean(false)),
+            (r#""A" < "B""#, Object::Boolean(true)),
+            (r#""B" > "AB""#, Object::Boolean(true)),
+            (
+                r#"
+                LET GREET = FUNC NAME DO "HELLO, " + NAME END
+                GREET("WORLD")
+                "#,
+                Object::String(String::from("HELLO, WORLD")),
+            ),
+            (
+                r#""A" - "B""#,
+                error("unknown operator: string - string", 1, 1, 9),
+            ),
+            (
+                r#""A" + 1"#,
+                error("type mismatch: string + integer", 1, 1, 7),
+            ),
+            (
+                r#"
+                LET S = "A"
+                WHILE TRUE DO
+                    LET S = S + S
+                END
+                "#,
+                error("string is longer than 10000 bytes", 4, 29, 5),
+            ),
+        ];
+
+        for (input, expected_output) in tests {
+            let object = test_eval(input);
+            assert_eq!(without_offset(object), expected_output);
+        }
+    }
+
+    #[test]
+    fn test_static_statements() {
+        let input = "
+            STATIC LET COUNT = 0
+            STATIC LET PHASE = 1
+            LET COUNT = COUNT + 1
+            IF COUNT > 2 DO
+                LET PHASE = 2
+            END
+            LET TEMPORARY = 1
+            COUNT
+            ";
+        let lexer = Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_program().unwrap();
+        let mut environment = Environment::new();
+        let mut evaluator = Evaluator::new();
+
+        for count in 1..=3 {
+            environment.clear();
+            let object = evaluator.eval(&program, &mut environment);
+            assert_eq!(object, Object::Integer(count));
+        }
+        assert_eq!(environment.get("PHASE"), Some(Object::Integer(2)));
+
+        environment.clear();
+        assert_eq!(environment.get("TEMPORARY"), None);
+        assert_eq!(environment.get("COUNT"), Some(Object::Integer(3)));
+
+        environment.reset();
+        let object = evaluator.eval(&program, &mut environment);
+        assert_eq!(object, Object::Integer(1));
+    }
+
+    // Creates an error at the given line and column, with the given length
+    fn error(message: &str, line: usize, column: usize, length: usize) -> Object {
+        Object::Error(RuntimeError::at(
+            String::from(message),
+            Span {
+                line,
+                column,
+                length,
+                offset: 0,
+            },
+        ))
+    }
+
+    // Errors are compared by their line and column, so the offset is ignored
+    fn without_offset(object: Object) -> Object {
+        match object {
+            Object::Error(RuntimeError {
+                message,
+                span: Some(span),
+            }) => Object::Error(RuntimeError::at(message, Span { offset: 0, ..span })),
+            _ => object,
+        }
+    }
+
     fn test_eval(input: &'static str) -> Object {
         let lexer = Lexer::new(input);
         let mut parser = Parser::new(lexer);
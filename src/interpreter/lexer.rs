@@ -1,35 +1,53 @@
-use crate::interpreter::token::Token;
+use crate::interpreter::token::{Span, Token};
 use std::iter::Peekable;
 use std::str::Chars;
 
 pub struct Lexer<'a> {
     input: Peekable<Chars<'a>>,
+    // The position of the next character
+    line: usize,
+    column: usize,
+    offset: usize,
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(input: &'a str) -> Lexer<'a> {
         Lexer {
             input: input.chars().peekable(),
+            line: 1,
+            column: 1,
+            offset: 0,
         }
     }
 
-    pub fn next_token(&mut self) -> Token {
+    /// Returns the next token, along with where it is in the input.
+    pub fn next_token(&mut self) -> (Token, Span) {
         if let Some(newline_token) = self.read_whitespace() {
             return newline_token;
         }
 
-        match self.input.next() {
+        let start = self.position();
+        let token = self.read_token();
+        let span = Span {
+            length: self.offset - start.offset,
+            ..start
+        };
+        (token, span)
+    }
+
+    fn read_token(&mut self) -> Token {
+        match self.next_char() {
             Some(';') => Token::Illegal,
             Some('=') => match self.input.peek() {
                 Some('=') => {
-                    self.input.next();
+                    self.next_char();
                     Token::Equal
                 }
                 _ => Token::Assign,
             },
             Some('!') => match self.input.peek() {
                 Some('=') => {
-                    self.input.next();
+                    self.next_char();
                     Token::NotEqual
                 }
                 _ => Token::Bang,
@@ -43,6 +61,7 @@ impl<'a> Lexer<'a> {
             Some('*') => Token::Asterisk,
             Some('<') => Token::LessThan,
             Some('>') => Token::GreaterThan,
+            Some('"') => self.read_string(),
             Some(char) => {
                 // If the first character is a alphabetic letter, we parse it as
                 // an identitier. If it's a digit we parse it as a number.
@@ -59,6 +78,13 @@ impl<'a> Lexer<'a> {
                         "RETURN" => Token::Return,
                         "DO" => Token::Do,
                         "END" => Token::End,
+                        "WHILE" => Token::While,
+                        "FOR" => Token::For,
+                        "IN" => Token::In,
+                        "RANGE" => Token::Range,
+                        "BREAK" => Token::Break,
+                        "CONTINUE" => Token::Continue,
+                        "STATIC" => Token::Static,
                         _ => Token::Identifier(identitier),
                     }
                 } else if char.is_digit(10) {
@@ -71,6 +97,33 @@ impl<'a> Lexer<'a> {
         }
     }
 
+    // An empty span at the position of the next character
+    fn position(&self) -> Span {
+        Span {
+            line: self.line,
+            column: self.column,
+            length: 0,
+            offset: self.offset,
+        }
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        let char = self.input.next();
+        match char {
+            Some('\n') => {
+                self.line += 1;
+                self.column = 1;
+                self.offset += 1;
+            }
+            Some(_) => {
+                self.column += 1;
+                self.offset += 1;
+            }
+            None => {}
+        }
+        char
+    }
+
     fn is_letter(&self, char: char) -> bool {
         char.is_alphabetic() || char == '_'
     }
@@ -81,7 +134,7 @@ impl<'a> Lexer<'a> {
 
         while let Some(&char) = self.input.peek() {
             if self.is_letter(char) {
-                match self.input.next() {
+                match self.next_char() {
                     Some(char) => identitier.push(char),
                     None => break,
                 }
@@ -100,13 +153,13 @@ impl<'a> Lexer<'a> {
 
         while let Some(&char) = self.input.peek() {
             if char.is_digit(10) {
-                match self.input.next() {
+                match self.next_char() {
                     Some(char) => chars.push(char),
                     None => break,
                 }
             } else if char == '.' {
                 is_floating_point = true;
-                match self.input.next() {
+                match self.next_char() {
                     Some(char) => chars.push(char),
                     None => break,
                 }
@@ -122,24 +175,67 @@ impl<'a> Lexer<'a> {
         }
     }
 
-    fn read_whitespace(&mut self) -> Option<Token> {
-        let mut contains_newline: bool = false;
+    // Reads a string literal up to the closing quote. Strings can't span
+    // multiple lines, so newlines have to be written as `\n`. Strings that
+    // aren't closed, or contain unknown escapes, are illegal.
+    fn read_string(&mut self) -> Token {
+        let mut string = String::new();
+        let mut error = None;
+        loop {
+            if let None | Some('\n') = self.input.peek() {
+                return Token::Invalid(String::from("unterminated string"));
+            }
+            match self.next_char() {
+                Some('"') => break,
+                Some('\\') => match self.input.peek() {
+                    Some('n') => string.push('\n'),
+                    Some('t') => string.push('\t'),
+                    Some('"') => string.push('"'),
+                    Some('\\') => string.push('\\'),
+                    Some('\n') | None => continue,
+                    Some(char) => {
+                        // Keep reading until the end of the string, so that
+                        // the error spans all of it.
+                        if error.is_none() {
+                            error = Some(format!("unknown escape `\\{}`", char));
+                        }
+                    }
+                },
+                Some(char) => {
+                    string.push(char);
+                    continue;
+                }
+                None => return Token::Invalid(String::from("unterminated string")),
+            }
+            // Skip the escaped character
+            self.next_char();
+        }
+
+        match error {
+            Some(message) => Token::Invalid(message),
+            None => Token::String(string),
+        }
+    }
+
+    // Skips any whitespace. Returns a newline token if there were any
+    // newlines, spanning the first one.
+    fn read_whitespace(&mut self) -> Option<(Token, Span)> {
+        let mut newline: Option<Span> = None;
         while let Some(&char) = self.input.peek() {
             if char.is_whitespace() {
                 // TODO: Look into checking for the unicode definition of newline
-                if char == '\n' {
-                    contains_newline = true;
+                if char == '\n' && newline.is_none() {
+                    newline = Some(Span {
+                        length: 1,
+                        ..self.position()
+                    });
                 }
-                self.input.next();
+                self.next_char();
             } else {
                 break;
             }
         }
-        if contains_newline {
-            Some(Token::Newline)
-        } else {
-            None
-        }
+        newline.map(|span| (Token::Newline, span))
     }
 }
 
@@ -255,7 +351,114 @@ fn test_next_token() {
     let mut lexer = Lexer::new(input);
 
     for expected_token in expected_tokens {
-        let token = lexer.next_token();
+        let (token, _) = lexer.next_token();
+        assert_eq!(token, expected_token);
+    }
+}
+
+#[test]
+fn test_spans() {
+    let input = "LET X = 10.5
+  IF X != Y DO";
+
+    let expected = vec![
+        (Token::Let, 1, 1, 3),
+        (Token::Identifier(String::from("X")), 1, 5, 1),
+        (Token::Assign, 1, 7, 1),
+        (Token::Float(String::from("10.5")), 1, 9, 4),
+        (Token::Newline, 1, 13, 1),
+        (Token::If, 2, 3, 2),
+        (Token::Identifier(String::from("X")), 2, 6, 1),
+        (Token::NotEqual, 2, 8, 2),
+        (Token::Identifier(String::from("Y")), 2, 11, 1),
+        (Token::Do, 2, 13, 2),
+        (Token::Eof, 2, 15, 0),
+    ];
+
+    let mut lexer = Lexer::new(input);
+
+    for (expected_token, line, column, length) in expected {
+        let (token, span) = lexer.next_token();
+        assert_eq!(token, expected_token);
+        assert_eq!(
+            (span.line, span.column, span.length),
+            (line, column, length),
+            "{:?}",
+            token
+        );
+    }
+}
+
+#[test]
+fn test_loop_keywords() {
+    let input = "WHILE TRUE DO BREAK END
+    FOR I IN RANGE(0, 10) DO CONTINUE END";
+
+    let expected_tokens = vec![
+        Token::While,
+        Token::True,
+        Token::Do,
+        Token::Break,
+        Token::End,
+        Token::Newline,
+        Token::For,
+        Token::Identifier(String::from("I")),
+        Token::In,
+        Token::Range,
+        Token::LeftParen,
+        Token::Int(String::from("0")),
+        Token::Comma,
+        Token::Int(String::from("10")),
+        Token::RightParen,
+        Token::Do,
+        Token::Continue,
+        Token::End,
+        Token::Eof,
+    ];
+
+    let mut lexer = Lexer::new(input);
+
+    for expected_token in expected_tokens {
+        let (token, _) = lexer.next_token();
+        assert_eq!(token, expected_token);
+    }
+}
+
+#[test]
+fn test_strings() {
+    let input = r#"LOG("ALTITUDE: ", "A \"B\"\n\t\\")
+"OPEN
+"BAD \X" 1"#;
+
+    let expected = vec![
+        (Token::Identifier(String::from("LOG")), 1, 1, 3),
+        (Token::LeftParen, 1, 4, 1),
+        (Token::String(String::from("ALTITUDE: ")), 1, 5, 12),
+        (Token::Comma, 1, 17, 1),
+        (Token::String(String::from("A \"B\"\n\t\\")), 1, 19, 15),
+        (Token::RightParen, 1, 34, 1),
+        (Token::Newline, 1, 35, 1),
+        (Token::Invalid(String::from("unterminated string")), 2, 1, 5),
+        (Token::Newline, 2, 6, 1),
+        (
+            Token::Invalid(String::from("unknown escape `\\X`")),
+            3,
+            1,
+            8,
+        ),
+        (Token::Int(String::from("1")), 3, 10, 1),
+    ];
+
+    let mut lexer = Lexer::new(input);
+
+    for (expected_token, line, column, length) in expected {
+        let (token, span) = lexer.next_token();
         assert_eq!(token, expected_token);
+        assert_eq!(
+            (span.line, span.column, span.length),
+            (line, column, length),
+            "{:?}",
+            token
+        );
     }
 }
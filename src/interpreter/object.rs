@@ -1,12 +1,14 @@
 use crate::interpreter::ast::BlockStatement;
+use crate::interpreter::token::Span;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt;
 
 #[derive(PartialEq, Debug, Clone)]
 pub enum Command {
     SetThrust { throttle: f64 },
     SetTorque { force: f64 },
+    Log { message: String },
 }
 
 pub type CommandFn = fn(Vec<Object>) -> Result<Command, String>;
@@ -16,7 +18,12 @@ pub enum Object {
     Integer(isize),
     Float(f64),
     Boolean(bool),
+    String(String),
     Return(Box<Object>),
+    // Returned from the statements in a loop body, to stop the loop or skip to
+    // the next iteration.
+    Break,
+    Continue,
     Error(RuntimeError),
     Function {
         parameters: Vec<String>,
@@ -35,7 +42,10 @@ impl PartialEq for Object {
             (Object::Integer(a), Object::Integer(b)) => a == b,
             (Object::Float(a), Object::Float(b)) => a == b,
             (Object::Boolean(a), Object::Boolean(b)) => a == b,
+            (Object::String(a), Object::String(b)) => a == b,
             (Object::Return(a), Object::Return(b)) => a == b,
+            (Object::Break, Object::Break) => true,
+            (Object::Continue, Object::Continue) => true,
             (Object::Error(a), Object::Error(b)) => a == b,
             (
                 Object::Function {
@@ -62,7 +72,10 @@ impl Object {
             Object::Integer(_) => String::from("integer"),
             Object::Float(_) => String::from("float"),
             Object::Boolean(_) => String::from("boolean"),
+            Object::String(_) => String::from("string"),
             Object::Return(_) => String::from("return"),
+            Object::Break => String::from("break"),
+            Object::Continue => String::from("continue"),
             Object::Error(_) => String::from("error"),
             Object::Function { .. } => String::from("function"),
             Object::Command { .. } => String::from("command"),
@@ -75,6 +88,9 @@ impl Object {
 pub struct Environment {
     store: HashMap<String, Object>,
     outer: Option<Box<Environment>>,
+    // Variables declared with STATIC, which are kept when the environment is
+    // cleared.
+    statics: HashSet<String>,
 }
 
 impl Environment {
@@ -82,6 +98,7 @@ impl Environment {
         Environment {
             store: HashMap::new(),
             outer: None,
+            statics: HashSet::new(),
         }
     }
 
@@ -89,6 +106,7 @@ impl Environment {
         Environment {
             store: HashMap::new(),
             outer: Some(Box::new(environment)),
+            statics: HashSet::new(),
         }
     }
 
@@ -106,6 +124,16 @@ impl Environment {
         self.store.insert(key, value);
     }
 
+    /// Sets a variable that is kept when the environment is cleared.
+    pub fn set_static(&mut self, key: String, value: Object) {
+        self.statics.insert(key.clone());
+        self.store.insert(key, value);
+    }
+
+    pub fn is_static(&self, key: &str) -> bool {
+        self.statics.contains(key)
+    }
+
     pub fn get_variables(&self) -> HashMap<String, ProgramVariable> {
         self.store
             .iter()
@@ -113,31 +141,77 @@ impl Environment {
                 Object::Integer(int) => Some((key.clone(), ProgramVariable::Integer(*int))),
                 Object::Boolean(bool) => Some((key.clone(), ProgramVariable::Boolean(*bool))),
                 Object::Float(float) => Some((key.clone(), ProgramVariable::Float(*float))),
+                Object::String(string) => {
+                    Some((key.clone(), ProgramVariable::String(string.clone())))
+                }
                 _ => None,
             })
             .collect()
     }
 
+    /// Returns the static variables that can be saved. Functions and commands
+    /// are left out.
+    pub fn get_statics(&self) -> BTreeMap<String, ProgramVariable> {
+        self.get_variables()
+            .into_iter()
+            .filter(|(key, _)| self.statics.contains(key))
+            .collect()
+    }
+
+    /// Removes every variable except the static ones.
     pub fn clear(&mut self) {
+        let statics = &self.statics;
+        self.store.retain(|key, _| statics.contains(key));
+    }
+
+    /// Removes every variable, including the static ones.
+    pub fn reset(&mut self) {
         self.store.clear();
+        self.statics.clear();
     }
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub enum ProgramVariable {
     Integer(isize),
     Float(f64),
     Boolean(bool),
+    String(String),
+}
+
+impl From<ProgramVariable> for Object {
+    fn from(variable: ProgramVariable) -> Self {
+        match variable {
+            ProgramVariable::Integer(value) => Object::Integer(value),
+            ProgramVariable::Float(value) => Object::Float(value),
+            ProgramVariable::Boolean(value) => Object::Boolean(value),
+            ProgramVariable::String(value) => Object::String(value),
+        }
+    }
 }
 
+// Serialized as `{ message, line, column, length }` once the evaluator knows
+// which expression the error came from.
 #[derive(Serialize, PartialEq, Debug, Clone)]
 pub struct RuntimeError {
     pub message: String,
+    #[serde(flatten)]
+    pub span: Option<Span>,
 }
 
 impl RuntimeError {
     pub fn new(message: String) -> RuntimeError {
-        RuntimeError { message }
+        RuntimeError {
+            message,
+            span: None,
+        }
+    }
+
+    pub fn at(message: String, span: Span) -> RuntimeError {
+        RuntimeError {
+            message,
+            span: Some(span),
+        }
     }
 }
 
@@ -149,7 +223,10 @@ impl fmt::Display for Object {
             Object::Integer(value) => write!(f, "{}", value),
             Object::Float(value) => write!(f, "{}", value),
             Object::Boolean(value) => write!(f, "{}", value),
+            Object::String(value) => write!(f, "{}", value),
             Object::Return(expression) => write!(f, "{}", *expression),
+            Object::Break => write!(f, "break"),
+            Object::Continue => write!(f, "continue"),
             Object::Error(error) => write!(f, "Error: {}", error.message),
             Object::Function {
                 parameters, body, ..
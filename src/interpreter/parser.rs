@@ -1,10 +1,12 @@
 use crate::interpreter::ast::BlockStatement;
 use crate::interpreter::ast::Expression;
+use crate::interpreter::ast::ExpressionKind;
 use crate::interpreter::ast::Operator;
 use crate::interpreter::ast::Program;
 use crate::interpreter::ast::Statement;
+use crate::interpreter::ast::StatementKind;
 use crate::interpreter::lexer::Lexer;
-use crate::interpreter::token::Token;
+use crate::interpreter::token::{Span, Token};
 use serde::Serialize;
 
 type PrefixParseFn<'a> = fn(&mut Parser<'a>) -> Option<Expression>;
@@ -21,34 +23,58 @@ enum Precedence {
     Call,        // myFunction(x)
 }
 
+// Serialized as `{ message, line, column, length }`, so that the editor can
+// point out where the error is.
 #[derive(PartialEq, Eq, Debug, Clone, Serialize)]
 pub struct ParserError {
     message: String,
+    #[serde(flatten)]
+    span: Span,
 }
 
 impl ParserError {
-    pub fn new(message: String) -> ParserError {
-        ParserError { message }
+    pub fn new(message: String, span: Span) -> ParserError {
+        ParserError { message, span }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
     }
 }
 
 pub struct Parser<'a> {
     lexer: Lexer<'a>,
     current_token: Token,
+    current_span: Span,
     peek_token: Token,
+    peek_span: Span,
     errors: Vec<ParserError>,
+    // The number of loops around the current statement, used to check that
+    // BREAK and CONTINUE are inside a loop.
+    loop_depth: usize,
+    // Whether the current statement is inside a function, where STATIC isn't
+    // allowed.
+    in_function: bool,
 }
 
 impl<'a> Parser<'a> {
     pub fn new(mut lexer: Lexer) -> Parser {
-        let current_token = lexer.next_token();
-        let peek_token = lexer.next_token();
+        let (current_token, current_span) = lexer.next_token();
+        let (peek_token, peek_span) = lexer.next_token();
 
         Parser {
             lexer: lexer,
             current_token: current_token,
+            current_span,
             peek_token: peek_token,
+            peek_span,
             errors: vec![],
+            loop_depth: 0,
+            in_function: false,
         }
     }
 
@@ -80,11 +106,16 @@ impl<'a> Parser<'a> {
         match self.current_token {
             Token::Let => self.parse_let_statement(),
             Token::Return => self.parse_return_statement(),
+            Token::Static => self.parse_static_statement(),
+            Token::While => self.parse_while_statement(),
+            Token::For => self.parse_for_statement(),
+            Token::Break | Token::Continue => self.parse_loop_control_statement(),
             _ => self.parse_expression_statement(),
         }
     }
 
     fn parse_let_statement(&mut self) -> Option<Statement> {
+        let start = self.current_span;
         match self.peek_token.clone() {
             Token::Identifier(identifier) => {
                 self.next_token();
@@ -99,12 +130,13 @@ impl<'a> Parser<'a> {
                         return None;
                     }
 
-                    let statement = Statement::Let {
+                    let span = start.to(expression.span);
+                    let statement = StatementKind::Let {
                         identifier: identifier.to_string(),
                         expression: expression,
                     };
 
-                    return Some(statement);
+                    return Some(Statement::new(statement, span));
                 }
 
                 None
@@ -113,11 +145,40 @@ impl<'a> Parser<'a> {
         }
     }
 
+    fn parse_static_statement(&mut self) -> Option<Statement> {
+        let start = self.current_span;
+        if self.in_function {
+            let error = String::from("STATIC is only allowed outside of functions");
+            self.push_error(error, start);
+            return None;
+        }
+        if !self.expect_peek(Token::Let) {
+            return None;
+        }
+
+        let statement = self.parse_let_statement()?;
+        match statement.kind {
+            StatementKind::Let {
+                identifier,
+                expression,
+            } => Some(Statement::new(
+                StatementKind::Static {
+                    identifier,
+                    expression,
+                },
+                start.to(statement.span),
+            )),
+            _ => None,
+        }
+    }
+
     fn parse_return_statement(&mut self) -> Option<Statement> {
+        let start = self.current_span;
         self.next_token();
 
         if let Some(expression) = self.parse_expression(Precedence::Lowest) {
-            let statement = Statement::Return {
+            let span = start.to(expression.span);
+            let statement = StatementKind::Return {
                 expression: expression,
             };
 
@@ -125,21 +186,111 @@ impl<'a> Parser<'a> {
                 self.next_token();
             }
 
-            return Some(statement);
+            return Some(Statement::new(statement, span));
         }
 
         None
     }
 
+    fn parse_while_statement(&mut self) -> Option<Statement> {
+        let start = self.current_span;
+        self.next_token();
+
+        let condition = self.parse_expression(Precedence::Lowest)?;
+        if !self.expect_peek(Token::Do) {
+            return None;
+        }
+        let (body, end) = self.parse_loop_body()?;
+
+        let statement = StatementKind::While { condition, body };
+        Some(Statement::new(statement, start.to(end)))
+    }
+
+    fn parse_for_statement(&mut self) -> Option<Statement> {
+        let start = self.current_span;
+        let identifier = match self.peek_token.clone() {
+            Token::Identifier(identifier) => identifier,
+            _ => {
+                let error = format!("Expected loop variable, got {:?} instead", self.peek_token);
+                self.push_error(error, self.peek_span);
+                return None;
+            }
+        };
+        self.next_token();
+
+        if !self.expect_peek(Token::In)
+            || !self.expect_peek(Token::Range)
+            || !self.expect_peek(Token::LeftParen)
+        {
+            return None;
+        }
+        self.next_token();
+        let range_start = self.parse_expression(Precedence::Lowest)?;
+        if !self.expect_peek(Token::Comma) {
+            return None;
+        }
+        self.next_token();
+        let range_end = self.parse_expression(Precedence::Lowest)?;
+        if !self.expect_peek(Token::RightParen) || !self.expect_peek(Token::Do) {
+            return None;
+        }
+        let (body, end) = self.parse_loop_body()?;
+
+        let statement = StatementKind::For {
+            identifier,
+            start: range_start,
+            end: range_end,
+            body,
+        };
+        Some(Statement::new(statement, start.to(end)))
+    }
+
+    // Parses the body of a loop, from DO up to and including END. Returns the
+    // body along with the span of END.
+    fn parse_loop_body(&mut self) -> Option<(BlockStatement, Span)> {
+        self.loop_depth += 1;
+        let body = self.parse_block_statement();
+        self.loop_depth -= 1;
+
+        if !self.expect_current(Token::End) {
+            return None;
+        }
+        let end = self.current_span;
+        if self.peek_token == Token::Newline {
+            self.next_token();
+        }
+        Some((body, end))
+    }
+
+    fn parse_loop_control_statement(&mut self) -> Option<Statement> {
+        let span = self.current_span;
+        let (statement, keyword) = if self.current_token == Token::Break {
+            (StatementKind::Break, "BREAK")
+        } else {
+            (StatementKind::Continue, "CONTINUE")
+        };
+
+        if self.loop_depth == 0 {
+            let error = format!("{} is only allowed inside a loop", keyword);
+            self.push_error(error, span);
+            return None;
+        }
+        if self.peek_token == Token::Newline {
+            self.next_token();
+        }
+        Some(Statement::new(statement, span))
+    }
+
     fn parse_expression_statement(&mut self) -> Option<Statement> {
         if let Some(expression) = self.parse_expression(Precedence::Lowest) {
             if self.peek_token == Token::Newline {
                 self.next_token();
             }
-            let statement = Statement::Expression {
+            let span = expression.span;
+            let statement = StatementKind::Expression {
                 expression: expression,
             };
-            Some(statement)
+            Some(Statement::new(statement, span))
         } else {
             None
         }
@@ -178,6 +329,7 @@ impl<'a> Parser<'a> {
             Token::Identifier(_) => Some(Parser::parse_identifier),
             Token::Int(_) => Some(Parser::parse_integer),
             Token::Float(_) => Some(Parser::parse_float),
+            Token::String(_) => Some(Parser::parse_string),
             Token::Bang | Token::Minus => Some(Parser::parse_prefix_expression),
             Token::True | Token::False => Some(Parser::parse_boolean),
             Token::LeftParen => Some(Parser::parse_grouped_expression),
@@ -185,12 +337,16 @@ impl<'a> Parser<'a> {
             Token::Function => Some(Parser::parse_function_expression),
             Token::Newline => None,
             Token::Eof => None,
+            Token::Invalid(message) => {
+                self.push_error(message.clone(), self.current_span);
+                None
+            }
             _ => {
                 let error = format!(
                     "No prefix parse function found for {:?}",
                     self.current_token
                 );
-                self.push_error(error);
+                self.push_error(error, self.current_span);
                 None
             }
         }
@@ -213,7 +369,10 @@ impl<'a> Parser<'a> {
 
     fn parse_identifier(&mut self) -> Option<Expression> {
         match &self.current_token {
-            Token::Identifier(name) => Some(Expression::Identifier(name.clone())),
+            Token::Identifier(name) => Some(Expression::new(
+                ExpressionKind::Identifier(name.clone()),
+                self.current_span,
+            )),
             _ => None,
         }
     }
@@ -221,10 +380,13 @@ impl<'a> Parser<'a> {
     fn parse_integer(&mut self) -> Option<Expression> {
         match &self.current_token {
             Token::Int(value) => match value.parse::<isize>() {
-                Ok(literal) => Some(Expression::Int(literal)),
+                Ok(literal) => Some(Expression::new(
+                    ExpressionKind::Int(literal),
+                    self.current_span,
+                )),
                 Err(_) => {
                     let error = format!("Could not parse {} as integer", value);
-                    self.push_error(error);
+                    self.push_error(error, self.current_span);
                     None
                 }
             },
@@ -235,10 +397,13 @@ impl<'a> Parser<'a> {
     fn parse_float(&mut self) -> Option<Expression> {
         match &self.current_token {
             Token::Float(value) => match value.parse::<f64>() {
-                Ok(literal) => Some(Expression::Float(literal)),
+                Ok(literal) => Some(Expression::new(
+                    ExpressionKind::Float(literal),
+                    self.current_span,
+                )),
                 Err(_) => {
                     let error = format!("Could not parse {} as float", value);
-                    self.push_error(error);
+                    self.push_error(error, self.current_span);
                     None
                 }
             },
@@ -246,25 +411,43 @@ impl<'a> Parser<'a> {
         }
     }
 
-    fn parse_boolean(&mut self) -> Option<Expression> {
+    fn parse_string(&mut self) -> Option<Expression> {
         match &self.current_token {
-            Token::True => Some(Expression::Boolean(true)),
-            Token::False => Some(Expression::Boolean(false)),
+            Token::String(string) => Some(Expression::new(
+                ExpressionKind::String(string.clone()),
+                self.current_span,
+            )),
             _ => None,
         }
     }
 
+    fn parse_boolean(&mut self) -> Option<Expression> {
+        let value = match &self.current_token {
+            Token::True => true,
+            Token::False => false,
+            _ => return None,
+        };
+        Some(Expression::new(
+            ExpressionKind::Boolean(value),
+            self.current_span,
+        ))
+    }
+
     fn parse_prefix_expression(&mut self) -> Option<Expression> {
         let operator = Operator::from(&self.current_token).unwrap();
+        let start = self.current_span;
         // The current token is either ! or - here, so we move to the next token
         // and parse that as an expression.
         self.next_token();
 
         match self.parse_expression(Precedence::Prefix) {
-            Some(expression) => Some(Expression::Prefix {
-                operator: operator,
-                right: Box::new(expression),
-            }),
+            Some(expression) => Some(Expression::new(
+                ExpressionKind::Prefix {
+                    operator: operator,
+                    right: Box::new(expression.clone()),
+                },
+                start.to(expression.span),
+            )),
             None => None,
         }
     }
@@ -275,21 +458,32 @@ impl<'a> Parser<'a> {
         self.next_token();
 
         match self.parse_expression(precedence) {
-            Some(right) => Some(Expression::Infix {
-                operator: operator,
-                left: Box::new(left),
-                right: Box::new(right),
-            }),
+            Some(right) => {
+                let span = left.span.to(right.span);
+                Some(Expression::new(
+                    ExpressionKind::Infix {
+                        operator: operator,
+                        left: Box::new(left),
+                        right: Box::new(right),
+                    },
+                    span,
+                ))
+            }
             None => None,
         }
     }
 
     fn parse_grouped_expression(&mut self) -> Option<Expression> {
+        let start = self.current_span;
         self.next_token();
 
         if let Some(expression) = self.parse_expression(Precedence::Lowest) {
             if self.expect_peek(Token::RightParen) {
-                Some(expression)
+                // The parentheses are included in the span
+                Some(Expression::new(
+                    expression.kind,
+                    start.to(self.current_span),
+                ))
             } else {
                 None
             }
@@ -299,6 +493,7 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_if_expression(&mut self) -> Option<Expression> {
+        let start = self.current_span;
         self.next_token();
 
         if let Some(condition) = self.parse_expression(Precedence::Lowest) {
@@ -311,18 +506,24 @@ impl<'a> Parser<'a> {
                 let alternative = self.parse_block_statement();
 
                 if self.expect_current(Token::End) {
-                    return Some(Expression::If {
-                        condition: Box::new(condition),
-                        consequence: consequence,
-                        alternative: Some(alternative),
-                    });
+                    return Some(Expression::new(
+                        ExpressionKind::If {
+                            condition: Box::new(condition),
+                            consequence: consequence,
+                            alternative: Some(alternative),
+                        },
+                        start.to(self.current_span),
+                    ));
                 }
             } else if self.expect_current(Token::End) {
-                return Some(Expression::If {
-                    condition: Box::new(condition),
-                    consequence: consequence,
-                    alternative: None,
-                });
+                return Some(Expression::new(
+                    ExpressionKind::If {
+                        condition: Box::new(condition),
+                        consequence: consequence,
+                        alternative: None,
+                    },
+                    start.to(self.current_span),
+                ));
             }
         }
 
@@ -330,16 +531,27 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_function_expression(&mut self) -> Option<Expression> {
+        let start = self.current_span;
         if let Some(parameters) = self.parse_function_parameters() {
             if !self.expect_peek(Token::Do) {
                 return None;
             }
+            // Loops outside of the function can't be controlled from inside it
+            let loop_depth = self.loop_depth;
+            let in_function = self.in_function;
+            self.loop_depth = 0;
+            self.in_function = true;
             let body = self.parse_block_statement();
+            self.loop_depth = loop_depth;
+            self.in_function = in_function;
             if self.expect_current(Token::End) {
-                return Some(Expression::Function {
-                    parameters: parameters,
-                    body: body,
-                });
+                return Some(Expression::new(
+                    ExpressionKind::Function {
+                        parameters: parameters,
+                        body: body,
+                    },
+                    start.to(self.current_span),
+                ));
             }
         }
 
@@ -355,20 +567,20 @@ impl<'a> Parser<'a> {
 
         let mut identifiers: Vec<String> = vec![];
 
-        if let Some(Expression::Identifier(identifier)) = self.parse_identifier() {
-            identifiers.push(identifier);
+        if let Token::Identifier(identifier) = &self.current_token {
+            identifiers.push(identifier.clone());
 
             while self.peek_token != Token::Do {
                 self.next_token();
 
-                if let Some(Expression::Identifier(identifier)) = self.parse_identifier() {
-                    identifiers.push(identifier);
+                if let Token::Identifier(identifier) = &self.current_token {
+                    identifiers.push(identifier.clone());
                 } else {
                     let error = format!(
                         "Expected function argument, got {:?} instead",
                         self.current_token
                     );
-                    self.push_error(error);
+                    self.push_error(error, self.current_span);
                     return None;
                 }
             }
@@ -377,7 +589,7 @@ impl<'a> Parser<'a> {
                 "Expected function argument, got {:?} instead",
                 self.current_token
             );
-            self.push_error(error);
+            self.push_error(error, self.current_span);
             return None;
         }
 
@@ -405,10 +617,15 @@ impl<'a> Parser<'a> {
 
     fn parse_call_expression(&mut self, function: Expression) -> Option<Expression> {
         let arguments = self.parse_call_arguments();
-        Some(Expression::Call {
-            function: Box::new(function),
-            arguments: arguments,
-        })
+        // The current token is the closing parenthesis
+        let span = function.span.to(self.current_span);
+        Some(Expression::new(
+            ExpressionKind::Call {
+                function: Box::new(function),
+                arguments: arguments,
+            },
+            span,
+        ))
     }
 
     fn parse_call_arguments(&mut self) -> Vec<Expression> {
@@ -442,7 +659,8 @@ impl<'a> Parser<'a> {
 
     fn next_token(&mut self) {
         self.current_token = self.peek_token.clone();
-        self.peek_token = self.lexer.next_token();
+        self.current_span = self.peek_span;
+        (self.peek_token, self.peek_span) = self.lexer.next_token();
     }
 
     fn expect_peek(&mut self, token: Token) -> bool {
@@ -460,7 +678,7 @@ impl<'a> Parser<'a> {
             "Expected next token to be {:?}, got {:?} instead",
             token, self.peek_token
         );
-        self.push_error(error);
+        self.push_error(error, self.peek_span);
     }
 
     fn expect_current(&mut self, token: Token) -> bool {
@@ -477,7 +695,7 @@ impl<'a> Parser<'a> {
             "Expected current token to be {:?}, got {:?} instead",
             token, self.current_token
         );
-        self.push_error(error);
+        self.push_error(error, self.current_span);
     }
 
     fn peek_precedence(&self) -> Precedence {
@@ -503,12 +721,46 @@ impl<'a> Parser<'a> {
         }
     }
 
-    fn push_error(&mut self, message: String) {
-        let error = ParserError::new(message);
+    fn push_error(&mut self, message: String, span: Span) {
+        let error = ParserError::new(message, span);
         self.errors.push(error);
     }
 }
 
+#[test]
+fn test_readme_examples() {
+    // Code blocks without a language are programs, and should all parse
+    let readme = include_str!("../../README.md");
+    let mut blocks = vec![];
+    let mut block: Option<String> = None;
+    for line in readme.lines() {
+        match (&mut block, line.starts_with("```")) {
+            (None, true) if line == "```" => block = Some(String::new()),
+            (None, true) => block = Some(String::from("```")),
+            (Some(source), true) => {
+                if !source.starts_with("```") {
+                    blocks.push(source.clone());
+                }
+                block = None;
+            }
+            (Some(source), false) => {
+                source.push_str(line);
+                source.push('\n');
+            }
+            (None, false) => {}
+        }
+    }
+
+    assert!(blocks.len() >= 3);
+    for source in blocks {
+        let lexer = Lexer::new(&source);
+        let mut parser = Parser::new(lexer);
+        if let Err(errors) = parser.parse_program() {
+            panic!("{:?} in README example:\n{}", errors, source);
+        }
+    }
+}
+
 #[test]
 fn test_new() {
     let lexer = Lexer::new("LET A = 1");
@@ -533,18 +785,21 @@ fn test_let_statement() {
     assert_eq!(
         program.statements,
         vec![
-            Statement::Let {
+            StatementKind::Let {
                 identifier: String::from("X"),
-                expression: Expression::Int(5),
-            },
-            Statement::Let {
+                expression: ExpressionKind::Int(5).into(),
+            }
+            .into(),
+            StatementKind::Let {
                 identifier: String::from("Y"),
-                expression: Expression::Int(10),
-            },
-            Statement::Let {
+                expression: ExpressionKind::Int(10).into(),
+            }
+            .into(),
+            StatementKind::Let {
                 identifier: String::from("FOOBAR"),
-                expression: Expression::Int(838383),
+                expression: ExpressionKind::Int(838383).into(),
             }
+            .into()
         ]
     );
 }
@@ -564,15 +819,18 @@ fn test_return_statement() {
     assert_eq!(
         program.statements,
         vec![
-            Statement::Return {
-                expression: Expression::Int(5),
-            },
-            Statement::Return {
-                expression: Expression::Int(10),
-            },
-            Statement::Return {
-                expression: Expression::Int(993322),
+            StatementKind::Return {
+                expression: ExpressionKind::Int(5).into(),
+            }
+            .into(),
+            StatementKind::Return {
+                expression: ExpressionKind::Int(10).into(),
+            }
+            .into(),
+            StatementKind::Return {
+                expression: ExpressionKind::Int(993322).into(),
             }
+            .into()
         ]
     );
 }
@@ -586,9 +844,10 @@ fn test_identifier_expression() {
 
     assert_eq!(
         program.statements,
-        vec![Statement::Expression {
-            expression: Expression::Identifier(String::from("foobar"))
-        }]
+        vec![StatementKind::Expression {
+            expression: ExpressionKind::Identifier(String::from("foobar")).into()
+        }
+        .into()]
     );
 }
 
@@ -601,9 +860,10 @@ fn test_integer_expression() {
 
     assert_eq!(
         program.statements,
-        vec![Statement::Expression {
-            expression: Expression::Int(5)
-        }]
+        vec![StatementKind::Expression {
+            expression: ExpressionKind::Int(5).into()
+        }
+        .into()]
     );
 }
 
@@ -616,9 +876,10 @@ fn test_float_expression() {
 
     assert_eq!(
         program.statements,
-        vec![Statement::Expression {
-            expression: Expression::Float(1.0)
-        }]
+        vec![StatementKind::Expression {
+            expression: ExpressionKind::Float(1.0).into()
+        }
+        .into()]
     );
 }
 
@@ -635,18 +896,22 @@ fn test_prefix_expression() {
     assert_eq!(
         program.statements,
         vec![
-            Statement::Expression {
-                expression: Expression::Prefix {
+            StatementKind::Expression {
+                expression: ExpressionKind::Prefix {
                     operator: Operator::Not,
-                    right: Box::new(Expression::Int(5))
+                    right: Box::new(ExpressionKind::Int(5).into())
                 }
-            },
-            Statement::Expression {
-                expression: Expression::Prefix {
+                .into()
+            }
+            .into(),
+            StatementKind::Expression {
+                expression: ExpressionKind::Prefix {
                     operator: Operator::Minus,
-                    right: Box::new(Expression::Int(15))
+                    right: Box::new(ExpressionKind::Int(15).into())
                 }
+                .into()
             }
+            .into()
         ]
     );
 }
@@ -681,13 +946,15 @@ fn test_infix_expression() {
     for (index, (operator, left, right)) in expected_expressions.iter().enumerate() {
         assert_eq!(
             program.statements[index],
-            Statement::Expression {
-                expression: Expression::Infix {
+            StatementKind::Expression {
+                expression: ExpressionKind::Infix {
                     operator: *operator,
-                    left: Box::new(Expression::Int(*left)),
-                    right: Box::new(Expression::Int(*right))
+                    left: Box::new(ExpressionKind::Int(*left).into()),
+                    right: Box::new(ExpressionKind::Int(*right).into())
                 }
-            },
+                .into()
+            }
+            .into(),
         )
     }
 }
@@ -705,12 +972,14 @@ fn test_boolean_expression() {
     assert_eq!(
         program.statements,
         vec![
-            Statement::Expression {
-                expression: Expression::Boolean(true)
-            },
-            Statement::Expression {
-                expression: Expression::Boolean(false)
+            StatementKind::Expression {
+                expression: ExpressionKind::Boolean(true).into()
+            }
+            .into(),
+            StatementKind::Expression {
+                expression: ExpressionKind::Boolean(false).into()
             }
+            .into()
         ]
     );
 }
@@ -778,21 +1047,27 @@ fn test_if_expression() {
 
     assert_eq!(
         program.statements,
-        vec![Statement::Expression {
-            expression: Expression::If {
-                condition: Box::new(Expression::Infix {
-                    operator: Operator::LessThan,
-                    left: Box::new(Expression::Identifier(String::from("X"))),
-                    right: Box::new(Expression::Identifier(String::from("Y"))),
-                }),
+        vec![StatementKind::Expression {
+            expression: ExpressionKind::If {
+                condition: Box::new(
+                    ExpressionKind::Infix {
+                        operator: Operator::LessThan,
+                        left: Box::new(ExpressionKind::Identifier(String::from("X")).into()),
+                        right: Box::new(ExpressionKind::Identifier(String::from("Y")).into()),
+                    }
+                    .into()
+                ),
                 consequence: BlockStatement {
-                    statements: vec![Statement::Expression {
-                        expression: Expression::Identifier(String::from("X"))
-                    }]
+                    statements: vec![StatementKind::Expression {
+                        expression: ExpressionKind::Identifier(String::from("X")).into()
+                    }
+                    .into()]
                 },
                 alternative: None
             }
-        },]
+            .into()
+        }
+        .into(),]
     );
 }
 
@@ -805,25 +1080,32 @@ fn test_if_else_expression() {
 
     assert_eq!(
         program.statements,
-        vec![Statement::Expression {
-            expression: Expression::If {
-                condition: Box::new(Expression::Infix {
-                    operator: Operator::LessThan,
-                    left: Box::new(Expression::Identifier(String::from("X"))),
-                    right: Box::new(Expression::Identifier(String::from("Y"))),
-                }),
+        vec![StatementKind::Expression {
+            expression: ExpressionKind::If {
+                condition: Box::new(
+                    ExpressionKind::Infix {
+                        operator: Operator::LessThan,
+                        left: Box::new(ExpressionKind::Identifier(String::from("X")).into()),
+                        right: Box::new(ExpressionKind::Identifier(String::from("Y")).into()),
+                    }
+                    .into()
+                ),
                 consequence: BlockStatement {
-                    statements: vec![Statement::Expression {
-                        expression: Expression::Identifier(String::from("X"))
-                    }]
+                    statements: vec![StatementKind::Expression {
+                        expression: ExpressionKind::Identifier(String::from("X")).into()
+                    }
+                    .into()]
                 },
                 alternative: Some(BlockStatement {
-                    statements: vec![Statement::Expression {
-                        expression: Expression::Identifier(String::from("Y"))
-                    }]
+                    statements: vec![StatementKind::Expression {
+                        expression: ExpressionKind::Identifier(String::from("Y")).into()
+                    }
+                    .into()]
                 })
             }
-        },]
+            .into()
+        }
+        .into(),]
     );
 }
 
@@ -836,20 +1118,24 @@ fn test_function_expression() {
 
     assert_eq!(
         program.statements,
-        vec![Statement::Expression {
-            expression: Expression::Function {
+        vec![StatementKind::Expression {
+            expression: ExpressionKind::Function {
                 parameters: vec![String::from("X"), String::from("Y"),],
                 body: BlockStatement {
-                    statements: vec![Statement::Expression {
-                        expression: Expression::Infix {
+                    statements: vec![StatementKind::Expression {
+                        expression: ExpressionKind::Infix {
                             operator: Operator::Plus,
-                            left: Box::new(Expression::Identifier(String::from("X"))),
-                            right: Box::new(Expression::Identifier(String::from("Y"))),
+                            left: Box::new(ExpressionKind::Identifier(String::from("X")).into()),
+                            right: Box::new(ExpressionKind::Identifier(String::from("Y")).into()),
                         }
-                    }]
+                        .into()
+                    }
+                    .into()]
                 }
             }
-        },]
+            .into()
+        }
+        .into(),]
     );
 }
 
@@ -871,8 +1157,16 @@ fn test_function_parameters() {
         let statement = program.statements.first().unwrap();
 
         match statement {
-            Statement::Expression {
-                expression: Expression::Function { parameters, .. },
+            Statement {
+                kind:
+                    StatementKind::Expression {
+                        expression:
+                            Expression {
+                                kind: ExpressionKind::Function { parameters, .. },
+                                ..
+                            },
+                    },
+                ..
             } => {
                 assert_eq!(*parameters, expected_parameters);
             }
@@ -890,24 +1184,28 @@ fn test_call_expression() {
 
     assert_eq!(
         program.statements,
-        vec![Statement::Expression {
-            expression: Expression::Call {
-                function: Box::new(Expression::Identifier(String::from("ADD"))),
+        vec![StatementKind::Expression {
+            expression: ExpressionKind::Call {
+                function: Box::new(ExpressionKind::Identifier(String::from("ADD")).into()),
                 arguments: vec![
-                    Expression::Int(1),
-                    Expression::Infix {
+                    ExpressionKind::Int(1).into(),
+                    ExpressionKind::Infix {
                         operator: Operator::Multiply,
-                        left: Box::new(Expression::Int(2)),
-                        right: Box::new(Expression::Int(3)),
-                    },
-                    Expression::Infix {
+                        left: Box::new(ExpressionKind::Int(2).into()),
+                        right: Box::new(ExpressionKind::Int(3).into()),
+                    }
+                    .into(),
+                    ExpressionKind::Infix {
                         operator: Operator::Plus,
-                        left: Box::new(Expression::Int(4)),
-                        right: Box::new(Expression::Int(5)),
-                    },
+                        left: Box::new(ExpressionKind::Int(4).into()),
+                        right: Box::new(ExpressionKind::Int(5).into()),
+                    }
+                    .into(),
                 ]
             }
-        }]
+            .into()
+        }
+        .into()]
     );
 }
 
@@ -920,8 +1218,268 @@ fn test_parser_error() {
 
     assert_eq!(
         program,
-        Err(vec![ParserError::new(String::from(
-            "Expected function argument, got Eof instead"
-        ))])
+        Err(vec![ParserError::new(
+            String::from("Expected function argument, got Eof instead"),
+            Span {
+                line: 1,
+                column: 7,
+                length: 0,
+                offset: 6
+            }
+        )])
     )
 }
+
+#[test]
+fn test_spans() {
+    let input = "LET A = 1
+IF A > -2 DO
+  SET_THRUST((A + 1) * 2)
+END";
+    let lexer = Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program().unwrap();
+
+    // Returns the line, column and length of a span
+    fn position(span: Span) -> (usize, usize, usize) {
+        (span.line, span.column, span.length)
+    }
+
+    assert_eq!(position(program.statements[0].span), (1, 1, 9));
+    assert_eq!(position(program.statements[1].span), (2, 1, 42));
+
+    let (condition, consequence) = match &program.statements[1].kind {
+        StatementKind::Expression { expression } => match &expression.kind {
+            ExpressionKind::If {
+                condition,
+                consequence,
+                ..
+            } => (condition, consequence),
+            _ => panic!("expected an if expression"),
+        },
+        _ => panic!("expected an expression statement"),
+    };
+    assert_eq!(position(condition.span), (2, 4, 6));
+    match &condition.kind {
+        ExpressionKind::Infix { right, .. } => assert_eq!(position(right.span), (2, 8, 2)),
+        _ => panic!("expected an infix expression"),
+    }
+
+    let call = &consequence.statements[0];
+    assert_eq!(position(call.span), (3, 3, 23));
+    match &call.kind {
+        StatementKind::Expression { expression } => match &expression.kind {
+            ExpressionKind::Call { arguments, .. } => {
+                assert_eq!(position(arguments[0].span), (3, 14, 11));
+            }
+            _ => panic!("expected a call expression"),
+        },
+        _ => panic!("expected an expression statement"),
+    }
+}
+
+#[test]
+fn test_parser_error_position() {
+    let input = "LET A = 1\nLET B = 2 +";
+    let lexer = Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    let errors = parser.parse_program().unwrap_err();
+
+    assert_eq!(errors[0].span().line, 2);
+    assert_eq!(errors[0].span().column, 12);
+}
+
+#[test]
+fn test_invalid_string_errors() {
+    let tests = vec![
+        ("LOG(\"OPEN)\n", "unterminated string", 5, 6),
+        ("LOG(\"A \\Q B\")\n", "unknown escape `\\Q`", 5, 8),
+    ];
+
+    for (input, expected_message, column, length) in tests {
+        let lexer = Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+        let errors = parser.parse_program().unwrap_err();
+
+        assert_eq!(errors[0].message(), expected_message);
+        assert_eq!(errors[0].span().line, 1);
+        assert_eq!(errors[0].span().column, column);
+        assert_eq!(errors[0].span().length, length);
+    }
+}
+
+#[test]
+fn test_parser_error_serialization() {
+    let lexer = Lexer::new("LET A = )");
+    let mut parser = Parser::new(lexer);
+    let errors = parser.parse_program().unwrap_err();
+
+    assert_eq!(
+        serde_json::to_value(&errors[0]).unwrap(),
+        serde_json::json!({
+            "message": "No prefix parse function found for RightParen",
+            "line": 1,
+            "column": 9,
+            "length": 1,
+        })
+    );
+}
+
+#[test]
+fn test_while_statement() {
+    let input = "WHILE X < 10 DO
+    LET X = X + 1
+END";
+    let lexer = Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program().unwrap();
+
+    assert_eq!(
+        program.statements,
+        vec![StatementKind::While {
+            condition: ExpressionKind::Infix {
+                operator: Operator::LessThan,
+                left: Box::new(ExpressionKind::Identifier(String::from("X")).into()),
+                right: Box::new(ExpressionKind::Int(10).into()),
+            }
+            .into(),
+            body: BlockStatement {
+                statements: vec![StatementKind::Let {
+                    identifier: String::from("X"),
+                    expression: ExpressionKind::Infix {
+                        operator: Operator::Plus,
+                        left: Box::new(ExpressionKind::Identifier(String::from("X")).into()),
+                        right: Box::new(ExpressionKind::Int(1).into()),
+                    }
+                    .into(),
+                }
+                .into()]
+            }
+        }
+        .into()]
+    );
+}
+
+#[test]
+fn test_for_statement() {
+    let input = "FOR I IN RANGE(0, N) DO
+    IF I > 5 DO BREAK END
+    CONTINUE
+END
+I";
+    let lexer = Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program().unwrap();
+
+    assert_eq!(
+        program.statements,
+        vec![
+            StatementKind::For {
+                identifier: String::from("I"),
+                start: ExpressionKind::Int(0).into(),
+                end: ExpressionKind::Identifier(String::from("N")).into(),
+                body: BlockStatement {
+                    statements: vec![
+                        StatementKind::Expression {
+                            expression: ExpressionKind::If {
+                                condition: Box::new(
+                                    ExpressionKind::Infix {
+                                        operator: Operator::GreaterThan,
+                                        left: Box::new(
+                                            ExpressionKind::Identifier(String::from("I")).into()
+                                        ),
+                                        right: Box::new(ExpressionKind::Int(5).into()),
+                                    }
+                                    .into()
+                                ),
+                                consequence: BlockStatement {
+                                    statements: vec![StatementKind::Break.into()]
+                                },
+                                alternative: None
+                            }
+                            .into()
+                        }
+                        .into(),
+                        StatementKind::Continue.into(),
+                    ]
+                }
+            }
+            .into(),
+            StatementKind::Expression {
+                expression: ExpressionKind::Identifier(String::from("I")).into()
+            }
+            .into(),
+        ]
+    );
+    assert_eq!(program.statements[0].span.length, 66);
+}
+
+#[test]
+fn test_loop_errors() {
+    let tests = vec![
+        ("BREAK", "BREAK is only allowed inside a loop"),
+        (
+            "WHILE TRUE DO LET F = FUNC DO CONTINUE END END",
+            "CONTINUE is only allowed inside a loop",
+        ),
+        (
+            "FOR 1 IN RANGE(0, 1) DO END",
+            "Expected loop variable, got Int(\"1\") instead",
+        ),
+        (
+            "FOR I IN (0, 1) DO END",
+            "Expected next token to be Range, got LeftParen instead",
+        ),
+    ];
+
+    for (input, expected_message) in tests {
+        let lexer = Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+        let errors = parser.parse_program().unwrap_err();
+
+        assert_eq!(errors[0].message(), expected_message);
+    }
+}
+
+#[test]
+fn test_static_statement() {
+    let input = "STATIC LET X = 5\nLET X = X + 1\n";
+    let lexer = Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program().unwrap();
+
+    assert_eq!(
+        program.statements[0],
+        StatementKind::Static {
+            identifier: String::from("X"),
+            expression: ExpressionKind::Int(5).into(),
+        }
+        .into()
+    );
+    assert_eq!(program.statements[0].span.length, 16);
+    assert_eq!(program.statements.len(), 2);
+
+    let input = "LET F = FUNC DO\nSTATIC LET X = 5\nEND\n";
+    let lexer = Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    let errors = parser.parse_program().unwrap_err();
+
+    assert_eq!(
+        errors[0].message(),
+        "STATIC is only allowed outside of functions"
+    );
+}
+
+#[test]
+fn test_string_expression() {
+    let input = r#"LOG("A\tB" + "C")"#;
+    let lexer = Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program().unwrap();
+
+    assert_eq!(program.to_string(), r#"LOG(("A\tB" + "C"))"#);
+    match &program.statements[0].kind {
+        StatementKind::Expression { expression } => assert_eq!(expression.span.length, 17),
+        _ => panic!("expected an expression statement"),
+    }
+}
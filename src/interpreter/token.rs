@@ -1,11 +1,17 @@
+use serde::Serialize;
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum Token {
     Illegal,
+    // A token that couldn't be read, with a message explaining why
+    Invalid(String),
     Eof,
     // Literals
     Identifier(String),
     Int(String),
     Float(String),
+    // The contents of a string literal, with any escapes already replaced
+    String(String),
     // Operators
     Assign,
     Plus,
@@ -32,4 +38,35 @@ pub enum Token {
     Return,
     Do,
     End,
+    While,
+    For,
+    In,
+    Range,
+    Break,
+    Continue,
+    Static,
+}
+
+/// Where a token, or an AST node made up of tokens, is in the source code.
+/// Lines and columns start at 1, and the length is in characters, including
+/// any newlines in between.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Serialize)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    pub length: usize,
+    // The number of characters before the start of the span, which is used to
+    // join spans together.
+    #[serde(skip)]
+    pub offset: usize,
+}
+
+impl Span {
+    /// Returns a span from the start of this span to the end of another one.
+    pub fn to(&self, end: Span) -> Span {
+        Span {
+            length: (end.offset + end.length).saturating_sub(self.offset),
+            ..*self
+        }
+    }
 }
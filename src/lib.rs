@@ -1,82 +1,25 @@
+#![cfg_attr(test, feature(test))]
+
+#[cfg(test)]
+mod benches;
+mod commands;
 mod components;
 mod entities;
+mod events;
+#[cfg(feature = "web")]
+mod game;
 mod helpers;
 mod interpreter;
+pub mod mission;
 mod query;
+mod registry;
+mod replay;
 mod resources;
 mod scene;
 mod scenes;
+mod schedule;
 mod systems;
 mod world;
 
-extern crate console_error_panic_hook;
-extern crate wasm_bindgen;
-
-use components::program::Program;
-use resources::input::{Input, KeyCode};
-use resources::viewport::Viewport;
-use scene::Scene;
-use scenes::scene_1;
-use wasm_bindgen::prelude::*;
-
-#[wasm_bindgen]
-pub struct Game {
-    scene: Scene,
-}
-
-#[wasm_bindgen]
-impl Game {
-    #[wasm_bindgen(constructor)]
-    pub fn new() -> Game {
-        console_error_panic_hook::set_once();
-        let scene = scene_1::generate_scene();
-        Game { scene }
-    }
-
-    pub fn change_program(&mut self, input: String) -> JsValue {
-        let mut programs = self.scene.world.query_mut::<&mut Program>();
-        let program = programs.get_mut(0).unwrap();
-        program.update(input);
-
-        if let Err(errors) = &program.program {
-            serde_wasm_bindgen::to_value(&errors).unwrap()
-        } else {
-            JsValue::null()
-        }
-    }
-
-    pub fn tick(&mut self) -> JsValue {
-        let viewport = self.scene.world.get_resource_mut::<Viewport>().unwrap();
-        viewport.move_toward_target();
-        for system in self.scene.systems.iter_mut() {
-            system.update(&mut self.scene.world);
-        }
-
-        let program = self.scene.world.query::<&Program>()[0];
-        if let Some(error) = &program.error {
-            serde_wasm_bindgen::to_value(&error).unwrap()
-        } else {
-            JsValue::null()
-        }
-    }
-
-    pub fn keydown(&mut self, key: String) {
-        let input = self.scene.world.get_resource_mut::<Input>().unwrap();
-        if let Some(key_code) = KeyCode::new(key.as_str()) {
-            input.press(key_code);
-        }
-    }
-
-    pub fn keyup(&mut self, key: String) {
-        let input = self.scene.world.get_resource_mut::<Input>().unwrap();
-        if let Some(key_code) = KeyCode::new(key.as_str()) {
-            input.release(key_code);
-        }
-    }
-}
-
-impl Default for Game {
-    fn default() -> Self {
-        Self::new()
-    }
-}
+#[cfg(feature = "web")]
+pub use game::Game;
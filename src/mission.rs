@@ -0,0 +1,348 @@
+use rapier2d::na::Vector2;
+use serde::Serialize;
+
+use crate::components::gravity::GravitySource;
+use crate::components::program::Program;
+use crate::components::rigid_body::RigidBody;
+use crate::components::thrusters::Thrusters;
+use crate::events::{EventReader, Events};
+use crate::interpreter::object::RuntimeError;
+use crate::query::With;
+use crate::resources::player::Player;
+use crate::resources::time::Time;
+use crate::scene::Scene;
+use crate::scenes::{create_scene, loader, SceneRegistry};
+use crate::systems::interpreter::{get_closest_gravity_source, ProgramErrored};
+
+/// Runs a scene with the player's program outside of the browser, one fixed
+/// simulation step at a time. Used by `void-run` to test programs.
+pub struct Mission {
+    scene: Scene,
+    errors: EventReader<ProgramErrored>,
+    // The first error the player's program ran into during the last step
+    error: Option<RuntimeError>,
+}
+
+/// The state of the player's ship after a simulation step. The altitude and
+/// angle are the ones a program sees as `ALTITUDE` and `ANGLE`, and are missing
+/// if there are no gravity sources in the scene.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Telemetry {
+    pub tick: u64,
+    /// The mission time, in milliseconds, like `TIME`.
+    pub time: f64,
+    pub x: f32,
+    pub y: f32,
+    pub velocity_x: f32,
+    pub velocity_y: f32,
+    pub fuel: Option<f64>,
+    pub altitude: Option<f32>,
+    pub angle: Option<f32>,
+}
+
+impl Mission {
+    /// Loads a scene file and replaces the player's program with the given
+    /// source. Returns an error if the scene couldn't be loaded, or if the
+    /// program couldn't be parsed, in which case every parser error is on its
+    /// own line, starting with its line and column.
+    pub fn new(scene_text: &str, source: &str) -> Result<Mission, String> {
+        let mut scene = create_scene(loader::load_playable_world(scene_text, 0)?);
+        {
+            let player = scene.world.get_resource::<Player>().unwrap().entity;
+            let program = scene.world.get_mut::<Program>(player).unwrap();
+            program.update(source.to_string());
+            if let Err(errors) = &program.program {
+                let messages: Vec<String> = errors
+                    .iter()
+                    .map(|error| {
+                        let span = error.span();
+                        format!("{}:{}: {}", span.line, span.column, error.message())
+                    })
+                    .collect();
+                return Err(messages.join("\n"));
+            }
+        }
+        let errors = scene
+            .world
+            .get_resource::<Events<ProgramErrored>>()
+            .unwrap()
+            .reader();
+        Ok(Mission {
+            scene,
+            errors,
+            error: None,
+        })
+    }
+
+    /// Runs a single simulation step.
+    pub fn step(&mut self) {
+        let step = self.scene.world.get_resource::<Time>().unwrap().step;
+        self.scene.update(step);
+
+        // Errors are read from the events rather than the program, so that an
+        // error is caught even if the program ran again without it.
+        let player = self.scene.world.get_resource::<Player>().unwrap().entity;
+        let events = self
+            .scene
+            .world
+            .get_resource::<Events<ProgramErrored>>()
+            .unwrap();
+        self.error = self
+            .errors
+            .read(&events)
+            .into_iter()
+            .find(|event| event.entity == player)
+            .map(|event| event.error.clone());
+    }
+
+    /// The runtime error the player's program ran into during the last step,
+    /// if any. Starts with its line and column, like parser errors, unless the
+    /// evaluator didn't know where it came from.
+    pub fn error(&self) -> Option<String> {
+        self.error.as_ref().map(|error| match &error.span {
+            Some(span) => format!("{}:{}: {}", span.line, span.column, error.message),
+            None => error.message.clone(),
+        })
+    }
+
+    pub fn telemetry(&self) -> Telemetry {
+        let world = &self.scene.world;
+        let time = world.get_resource::<Time>().unwrap();
+        let player = world.get_resource::<Player>().unwrap().entity;
+        let rigid_body = world
+            .get::<RigidBody>(player)
+            .expect("the player should have a rigid body");
+
+        let gravity_sources: Vec<Vector2<f32>> = world
+            .query::<(&RigidBody, With<GravitySource>)>()
+            .iter()
+            .map(|(rigid_body, _)| rigid_body.transform.position)
+            .collect();
+        let closest_gravity_source = get_closest_gravity_source(rigid_body, &gravity_sources);
+
+        Telemetry {
+            tick: time.ticks(),
+            time: (time.elapsed() * 1000.0).round(),
+            x: rigid_body.transform.position.x,
+            y: rigid_body.transform.position.y,
+            velocity_x: rigid_body.linear_velocity.x,
+            velocity_y: rigid_body.linear_velocity.y,
+            fuel: world
+                .get::<Thrusters>(player)
+                .map(|thrusters| thrusters.fuel),
+            altitude: closest_gravity_source
+                .as_ref()
+                .map(|source| source.distance),
+            angle: closest_gravity_source.map(|source| source.relative_angle.to_degrees()),
+        }
+    }
+}
+
+impl Telemetry {
+    pub fn csv_header() -> &'static str {
+        "tick,time,x,y,velocity_x,velocity_y,fuel,altitude,angle"
+    }
+
+    /// Formats the telemetry as a row matching `csv_header`. Missing values
+    /// are left empty.
+    pub fn to_csv(&self) -> String {
+        fn optional<T: ToString>(value: Option<T>) -> String {
+            value.map(|value| value.to_string()).unwrap_or_default()
+        }
+
+        format!(
+            "{},{},{},{},{},{},{},{},{}",
+            self.tick,
+            self.time,
+            self.x,
+            self.y,
+            self.velocity_x,
+            self.velocity_y,
+            optional(self.fuel),
+            optional(self.altitude),
+            optional(self.angle),
+        )
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+/// Returns the scene file of the built-in scene with the given name.
+pub fn built_in_scene(name: &str) -> Option<String> {
+    SceneRegistry::built_in().get(name).map(str::to_string)
+}
+
+/// Returns the program in a program file. The examples are Markdown files with
+/// the program in a fenced code block, in which case the first block is used.
+/// Anything else is treated as the program itself.
+pub fn program_source(text: &str) -> String {
+    let mut lines = text.lines();
+    if !lines.any(|line| line.trim_start().starts_with("```")) {
+        return text.to_string();
+    }
+
+    let mut source = String::new();
+    for line in lines.take_while(|line| !line.trim_start().starts_with("```")) {
+        source.push_str(line);
+        source.push('\n');
+    }
+    source
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SCENE: &str = r#"{
+        "entities": [
+            {
+                "rigid_body": { "position": [0.0, 0.0], "physics_mode": "Static" },
+                "gravity_source": { "strength": 1000.0 }
+            },
+            {
+                "player": true,
+                "rigid_body": { "position": [0.0, 100.0] },
+                "shape": {
+                    "vertices": [[-1.0, -1.0], [1.0, -1.0], [0.0, 1.0]],
+                    "color": { "r": 255, "g": 255, "b": 255, "a": 1.0 }
+                },
+                "thrusters": { "fuel": 10.0, "fuel_max": 10.0, "thrusters": [] },
+                "program": ""
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn test_step() {
+        let mut mission = Mission::new(SCENE, "SET_THRUST(0)").unwrap();
+        let start = mission.telemetry();
+        assert_eq!(start.tick, 0);
+        assert_eq!(start.altitude, Some(100.0));
+        assert_eq!(start.fuel, Some(10.0));
+
+        for _ in 0..60 {
+            mission.step();
+        }
+        let telemetry = mission.telemetry();
+        assert_eq!(telemetry.tick, 60);
+        assert_eq!(telemetry.time, 1000.0);
+        assert!(telemetry.velocity_y < 0.0);
+        assert!(telemetry.altitude.unwrap() < 100.0);
+        assert_eq!(mission.error(), None);
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = Mission::new(SCENE, "SET_THRUST(0)\nLET = 1").err().unwrap();
+        assert!(error.starts_with("2:5: "), "{}", error);
+        assert!(Mission::new(r#"{ "entities": [] }"#, "").is_err());
+
+        let mut mission = Mission::new(SCENE, "SET_THRUST(1, 2)").unwrap();
+        mission.step();
+        assert_eq!(
+            mission.error(),
+            Some(String::from(
+                "1:1: wrong number of arguments. got=2, want=1"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_without_gravity_sources() {
+        let scene = SCENE.replace(
+            r#""gravity_source": { "strength": 1000.0 }"#,
+            r#""name": "rock""#,
+        );
+        let mut mission = Mission::new(&scene, "SET_THRUST(0)").unwrap();
+        mission.step();
+        assert_eq!(mission.error(), None);
+        assert_eq!(mission.telemetry().altitude, None);
+
+        let mut mission = Mission::new(&scene, "LET HEIGHT = ALTITUDE\n").unwrap();
+        mission.step();
+        assert_eq!(
+            mission.error(),
+            Some(String::from("1:14: identifier not found: ALTITUDE"))
+        );
+    }
+
+    #[test]
+    fn test_cpu_cycles() {
+        let program = "FOR I IN RANGE(0, 100) DO SET_THRUST(0) END";
+        let mut mission = Mission::new(SCENE, program).unwrap();
+        mission.step();
+        assert_eq!(mission.error(), None);
+
+        let scene = SCENE.replace(
+            r#""program": """#,
+            r#""program": "", "computer": { "cycles": 50 }"#,
+        );
+        let mut mission = Mission::new(&scene, program).unwrap();
+        mission.step();
+        assert_eq!(
+            mission.error(),
+            Some(String::from("1:27: out of CPU cycles"))
+        );
+    }
+
+    #[test]
+    fn test_static_variables() {
+        let program =
+            "STATIC LET STEPS = 0\nLET STEPS = STEPS + 1\nIF STEPS > 3 DO SET_THRUST(1, 2) END";
+        let mut mission = Mission::new(SCENE, program).unwrap();
+        for _ in 0..3 {
+            mission.step();
+            assert_eq!(mission.error(), None);
+        }
+        mission.step();
+        assert!(mission.error().is_some());
+    }
+
+    #[test]
+    fn test_log() {
+        let program = "STATIC LET STEP = 0\nLET STEP = STEP + 1\nLOG(\"STEP\", STEP, STEP > 1)\n";
+        let mut mission = Mission::new(SCENE, program).unwrap();
+        mission.step();
+        mission.step();
+
+        let player = mission.scene.world.get_resource::<Player>().unwrap().entity;
+        let program = mission.scene.world.get::<Program>(player).unwrap();
+        assert_eq!(
+            program.console.lines(),
+            vec![String::from("STEP 1 false"), String::from("STEP 2 true")]
+        );
+    }
+
+    #[test]
+    fn test_telemetry_formats() {
+        let telemetry = Telemetry {
+            tick: 1,
+            time: 16.0,
+            x: 1.5,
+            y: -2.0,
+            velocity_x: 0.0,
+            velocity_y: 3.0,
+            fuel: None,
+            altitude: Some(10.0),
+            angle: None,
+        };
+        assert_eq!(telemetry.to_csv(), "1,16,1.5,-2,0,3,,10,");
+        assert_eq!(
+            telemetry.to_json(),
+            r#"{"tick":1,"time":16.0,"x":1.5,"y":-2.0,"velocity_x":0.0,"velocity_y":3.0,"fuel":null,"altitude":10.0,"angle":null}"#
+        );
+    }
+
+    #[test]
+    fn test_program_source() {
+        assert_eq!(program_source("SET_THRUST(1)"), "SET_THRUST(1)");
+        assert_eq!(
+            program_source(
+                "# Title\n\nText\n\n```\nLET X = 1\nSET_THRUST(X)\n```\n\n```\nOTHER\n```"
+            ),
+            "LET X = 1\nSET_THRUST(X)\n"
+        );
+    }
+}
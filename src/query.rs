@@ -1,69 +1,263 @@
-use crate::entities::Entities;
-use std::any::{Any, TypeId};
+use crate::entities::{Archetype, Column, Entities, Entity};
+use hashbrown::HashMap;
+use std::any::TypeId;
+use std::iter::{repeat_n, Copied, RepeatN};
+use std::marker::PhantomData;
+use std::slice::{Iter, IterMut};
 
 /// The Query trait allow us to provide an API in the World struct where you can
 /// query based on the generic types. For instead, you can query for a single
 /// component type using `query::<T>`, but if you need all entities matching two
 /// component types you can specify it as a tuple like this `query::<(&T, &U)>`.
+/// If you need to know which entity the components belong to, you can add
+/// `Entity` to the tuple like this `query::<(Entity, &T)>`.
+///
+/// Tuples can also contain `Option<&T>` for components that an entity might
+/// not have, as well as the `With<T>`, `Without<T>`, `Added<T>` and
+/// `Changed<T>` filters. Filters only decide which entities are included, and
+/// return `()` as their item.
 ///
 /// This is acheived by implementing the Query trait for the types we want to
-/// allow. If you see below, we have implemented for &T, (&T, &U) etc. The code
-/// gets a bit hairy, but the bonus is that our World API becomes very clean.
+/// allow. If you see below, we have implemented for T, and tuples of up to
+/// eight types using the `impl_query_tuple` macro, where each type implements
+/// the `Fetch` trait. The code gets a bit hairy, but the bonus is that our
+/// World API becomes very clean.
 pub trait Query<'a> {
     type QueryItem;
     fn query(entities: &'a Entities) -> Vec<Self::QueryItem>;
 }
 
-/// Supports querying for a single component type.
-impl<'a, T: 'static> Query<'a> for &T {
-    type QueryItem = &'a T;
+/// Supports querying for a single type.
+impl<'a, T: Fetch<'a>> Query<'a> for T {
+    type QueryItem = T::Item;
     fn query(entities: &'a Entities) -> Vec<Self::QueryItem> {
-        // Get all the components for the given generic type.
+        // Fetch the items from every archetype that matches the given generic
+        // type, and chain them together.
+        let last_change_tick = entities.last_change_tick;
         entities
-            .get_components::<T>()
-            .into_iter()
-            .flatten()
+            .archetypes
+            .iter()
+            .filter(|archetype| T::matches(archetype))
+            .flat_map(|archetype| {
+                T::fetch(archetype)
+                    .enumerate()
+                    .filter(move |(row, _)| T::includes(archetype, *row, last_change_tick))
+                    .map(|(_, item)| item)
+            })
             .collect()
     }
 }
 
-/// Supports querying two component types at the same time.
-impl<'a, T: 'static, U: 'static> Query<'a> for (&T, &U) {
-    type QueryItem = (&'a T, &'a U);
-    fn query(entities: &'a Entities) -> Vec<Self::QueryItem> {
-        // Get all the components for the two generic types in the tuple. Then
-        // zip them together into a single tuple.
-        let first = entities.get_components::<T>().into_iter();
-        let second = entities.get_components::<U>().into_iter();
-
-        first
-            .zip(second)
-            .filter_map(|option| match option {
-                (Some(a), Some(b)) => Some((a, b)),
-                _ => None,
-            })
-            .collect()
+/// Implements Query and QueryMut for a tuple of the given types. For each
+/// archetype that matches all the types we get an iterator from each of them,
+/// and step through them together to build up the tuples. Each type is paired
+/// with a variable name to hold its iterator. Rows that are filtered out still
+/// need to be stepped through to keep the iterators in sync.
+macro_rules! impl_query_tuple {
+    ($($name:ident $iter:ident),+) => {
+        impl<'a, $($name: Fetch<'a>),+> Query<'a> for ($($name,)+) {
+            type QueryItem = ($($name::Item,)+);
+            fn query(entities: &'a Entities) -> Vec<Self::QueryItem> {
+                let last_change_tick = entities.last_change_tick;
+                let mut result = vec![];
+                for archetype in entities.archetypes.iter() {
+                    if !($($name::matches(archetype))&&+) {
+                        continue;
+                    }
+                    $(let mut $iter = $name::fetch(archetype);)+
+                    for row in 0..archetype.entities.len() {
+                        let item = ($($iter.next().unwrap(),)+);
+                        if $($name::includes(archetype, row, last_change_tick))&&+ {
+                            result.push(item);
+                        }
+                    }
+                }
+                result
+            }
+        }
+
+        impl<'a, $($name: FetchMut<'a>),+> QueryMut<'a> for ($($name,)+) {
+            type QueryItem = ($($name::Item,)+);
+            fn query(entities: &'a mut Entities) -> Vec<Self::QueryItem> {
+                let change_tick = entities.change_tick;
+                let last_change_tick = entities.last_change_tick;
+                let mut result = vec![];
+                for archetype in entities.archetypes.iter_mut() {
+                    if !($($name::matches(archetype))&&+) {
+                        continue;
+                    }
+                    // Decide which rows to include before splitting up the
+                    // archetype, since we need to mark them as changed.
+                    let included: Vec<bool> = (0..archetype.entities.len())
+                        .map(|row| $($name::includes(archetype, row, last_change_tick))&&+)
+                        .collect();
+                    for (row, _) in included.iter().enumerate().filter(|(_, included)| **included) {
+                        $($name::mark_changed(archetype, row, change_tick);)+
+                    }
+
+                    let mut columns = ArchetypeColumns::new(archetype);
+                    $(let mut $iter = $name::fetch(&mut columns);)+
+                    for include in included {
+                        let item = ($($iter.next().unwrap(),)+);
+                        if include {
+                            result.push(item);
+                        }
+                    }
+                }
+                result
+            }
+        }
+    };
+}
+
+impl_query_tuple!(A a, B b);
+impl_query_tuple!(A a, B b, C c);
+impl_query_tuple!(A a, B b, C c, D d);
+impl_query_tuple!(A a, B b, C c, D d, E e);
+impl_query_tuple!(A a, B b, C c, D d, E e, F f);
+impl_query_tuple!(A a, B b, C c, D d, E e, F f, G g);
+impl_query_tuple!(A a, B b, C c, D d, E e, F f, G g, H h);
+
+/// Implemented for each type that can be part of a `Query`. It decides which
+/// archetypes the type matches, and how to get the items out of them.
+pub trait Fetch<'a> {
+    type Item;
+    type Iter: Iterator<Item = Self::Item>;
+    fn matches(archetype: &Archetype) -> bool;
+    fn fetch(archetype: &'a Archetype) -> Self::Iter;
+    /// Returns false if the entity at the given row should be left out. Only
+    /// the change detection filters need to look at each row, everything else
+    /// is decided by `matches`.
+    fn includes(_archetype: &Archetype, _row: usize, _last_change_tick: u64) -> bool {
+        true
     }
 }
 
-/// Supports querying three component types at the same time.
-impl<'a, T: 'static, U: 'static, V: 'static> Query<'a> for (&T, &U, &V) {
-    type QueryItem = (&'a T, &'a U, &'a V);
-    fn query(entities: &'a Entities) -> Vec<Self::QueryItem> {
-        // Get all the components for the three generic types in the tuple. Then
-        // zip them together into a single tuple.
-        let first = entities.get_components::<T>().into_iter();
-        let second = entities.get_components::<U>().into_iter();
-        let third = entities.get_components::<V>().into_iter();
-
-        first
-            .zip(second)
-            .zip(third)
-            .filter_map(|option| match option {
-                ((Some(a), Some(b)), Some(c)) => Some((a, b, c)),
-                _ => None,
-            })
-            .collect()
+/// Fetches a reference to each component of the given type.
+impl<'a, T: 'static> Fetch<'a> for &T {
+    type Item = &'a T;
+    type Iter = Iter<'a, T>;
+    fn matches(archetype: &Archetype) -> bool {
+        archetype.has(&TypeId::of::<T>())
+    }
+    fn fetch(archetype: &'a Archetype) -> Self::Iter {
+        archetype.column::<T>().unwrap().iter()
+    }
+}
+
+/// Fetches the handle of each entity, which matches every archetype.
+impl<'a> Fetch<'a> for Entity {
+    type Item = Entity;
+    type Iter = Copied<Iter<'a, Entity>>;
+    fn matches(_archetype: &Archetype) -> bool {
+        true
+    }
+    fn fetch(archetype: &'a Archetype) -> Self::Iter {
+        archetype.entities.iter().copied()
+    }
+}
+
+/// Fetches a reference to each component of the given type if the entity has
+/// one, which matches every archetype.
+impl<'a, T: 'static> Fetch<'a> for Option<&T> {
+    type Item = Option<&'a T>;
+    type Iter = OptionalIter<Iter<'a, T>>;
+    fn matches(_archetype: &Archetype) -> bool {
+        true
+    }
+    fn fetch(archetype: &'a Archetype) -> Self::Iter {
+        match archetype.column::<T>() {
+            Some(column) => OptionalIter::Present(column.iter()),
+            None => OptionalIter::Missing(archetype.entities.len()),
+        }
+    }
+}
+
+/// Filters the query to only include entities that have a component of the
+/// given type, without fetching the component.
+pub struct With<T>(PhantomData<T>);
+
+impl<'a, T: 'static> Fetch<'a> for With<T> {
+    type Item = ();
+    type Iter = RepeatN<()>;
+    fn matches(archetype: &Archetype) -> bool {
+        archetype.has(&TypeId::of::<T>())
+    }
+    fn fetch(archetype: &'a Archetype) -> Self::Iter {
+        repeat_n((), archetype.entities.len())
+    }
+}
+
+/// Filters the query to only include entities that don't have a component of
+/// the given type.
+pub struct Without<T>(PhantomData<T>);
+
+impl<'a, T: 'static> Fetch<'a> for Without<T> {
+    type Item = ();
+    type Iter = RepeatN<()>;
+    fn matches(archetype: &Archetype) -> bool {
+        !archetype.has(&TypeId::of::<T>())
+    }
+    fn fetch(archetype: &'a Archetype) -> Self::Iter {
+        repeat_n((), archetype.entities.len())
+    }
+}
+
+/// Filters the query to only include entities where the component of the
+/// given type has been added since the system last ran.
+pub struct Added<T>(PhantomData<T>);
+
+impl<'a, T: 'static> Fetch<'a> for Added<T> {
+    type Item = ();
+    type Iter = RepeatN<()>;
+    fn matches(archetype: &Archetype) -> bool {
+        archetype.has(&TypeId::of::<T>())
+    }
+    fn fetch(archetype: &'a Archetype) -> Self::Iter {
+        repeat_n((), archetype.entities.len())
+    }
+    fn includes(archetype: &Archetype, row: usize, last_change_tick: u64) -> bool {
+        archetype.ticks[&TypeId::of::<T>()][row].added > last_change_tick
+    }
+}
+
+/// Filters the query to only include entities where the component of the
+/// given type has been added, mutably queried or borrowed with `get_mut` since
+/// the system last ran.
+pub struct Changed<T>(PhantomData<T>);
+
+impl<'a, T: 'static> Fetch<'a> for Changed<T> {
+    type Item = ();
+    type Iter = RepeatN<()>;
+    fn matches(archetype: &Archetype) -> bool {
+        archetype.has(&TypeId::of::<T>())
+    }
+    fn fetch(archetype: &'a Archetype) -> Self::Iter {
+        repeat_n((), archetype.entities.len())
+    }
+    fn includes(archetype: &Archetype, row: usize, last_change_tick: u64) -> bool {
+        archetype.ticks[&TypeId::of::<T>()][row].changed > last_change_tick
+    }
+}
+
+/// Iterator for optional components. If the archetype has the component we
+/// wrap each item in Some, otherwise we return None once for each entity.
+pub enum OptionalIter<I> {
+    Present(I),
+    Missing(usize),
+}
+
+impl<I: Iterator> Iterator for OptionalIter<I> {
+    type Item = Option<I::Item>;
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            OptionalIter::Present(iter) => iter.next().map(Some),
+            OptionalIter::Missing(0) => None,
+            OptionalIter::Missing(remaining) => {
+                *remaining -= 1;
+                Some(None)
+            }
+        }
     }
 }
 
@@ -75,105 +269,234 @@ pub trait QueryMut<'a> {
     fn query(entities: &'a mut Entities) -> Vec<Self::QueryItem>;
 }
 
-/// Supports querying for a single component type (both &mut T or &T).
-impl<'a, T: Downcastable<'a>> QueryMut<'a> for T {
+/// Supports querying for a single type (both &mut T, &T or Entity).
+impl<'a, T: FetchMut<'a>> QueryMut<'a> for T {
     type QueryItem = T::Item;
     fn query(entities: &'a mut Entities) -> Vec<Self::QueryItem> {
-        let vec = entities.components.get_mut(&T::id()).unwrap();
-        T::downcast(vec).into_iter().flatten().collect()
-    }
-}
+        let change_tick = entities.change_tick;
+        let last_change_tick = entities.last_change_tick;
+        let mut result = vec![];
+        for archetype in entities.archetypes.iter_mut() {
+            if !T::matches(archetype) {
+                continue;
+            }
+            let included: Vec<bool> = (0..archetype.entities.len())
+                .map(|row| T::includes(archetype, row, last_change_tick))
+                .collect();
+            for (row, _) in included
+                .iter()
+                .enumerate()
+                .filter(|(_, included)| **included)
+            {
+                T::mark_changed(archetype, row, change_tick);
+            }
 
-/// Supports querying two component types at the same time.
-impl<'a, T: Downcastable<'a>, U: Downcastable<'a>> QueryMut<'a> for (T, U) {
-    type QueryItem = (T::Item, U::Item);
-    fn query(entities: &'a mut Entities) -> Vec<Self::QueryItem> {
-        if let [Ok(first), Ok(second)] = entities.components.get_each_mut([&T::id(), &U::id()]) {
-            let first = T::downcast(first).into_iter();
-            let second = U::downcast(second).into_iter();
-
-            first
-                .zip(second)
-                .filter_map(|option| match option {
-                    (Some(a), Some(b)) => Some((a, b)),
-                    _ => None,
-                })
-                .collect()
-        } else {
-            panic!("could not find any components of the given generic type. Make sure you have called register_component for all types.")
+            let items = T::fetch(&mut ArchetypeColumns::new(archetype));
+            for (item, include) in items.zip(included) {
+                if include {
+                    result.push(item);
+                }
+            }
         }
+        result
     }
 }
 
-/// Supports querying three component types at the same time.
-impl<'a, T: Downcastable<'a>, U: Downcastable<'a>, V: Downcastable<'a>> QueryMut<'a> for (T, U, V) {
-    type QueryItem = (T::Item, U::Item, V::Item);
-    fn query(entities: &'a mut Entities) -> Vec<Self::QueryItem> {
-        if let [Ok(first), Ok(second), Ok(third)] =
-            entities
-                .components
-                .get_each_mut([&T::id(), &U::id(), &V::id()])
-        {
-            let first = T::downcast(first).into_iter();
-            let second = U::downcast(second).into_iter();
-            let third = V::downcast(third).into_iter();
-            first
-                .zip(second)
-                .zip(third)
-                .filter_map(|option| match option {
-                    ((Some(a), Some(b)), Some(c)) => Some((a, b, c)),
-                    _ => None,
-                })
-                .collect()
-        } else {
-            panic!("could not find any components of the given generic type. Make sure you have called register_component for all types.")
+/// The mutable borrow of a single archetype, split up into its columns. Each
+/// type in a `QueryMut` takes out the column it needs, which is what allows us
+/// to hand out mutable references to several columns of the same archetype
+/// at the same time.
+pub struct ArchetypeColumns<'a> {
+    entities: &'a [Entity],
+    columns: HashMap<TypeId, &'a mut (dyn Column + 'static)>,
+}
+
+impl<'a> ArchetypeColumns<'a> {
+    fn new(archetype: &'a mut Archetype) -> Self {
+        ArchetypeColumns {
+            entities: &archetype.entities,
+            columns: archetype
+                .columns
+                .iter_mut()
+                .map(|(id, column)| (*id, column.as_mut()))
+                .collect(),
         }
     }
+
+    /// Takes out the column for the given type. Each column can only be taken
+    /// once, so querying the same component type twice will panic.
+    fn take(&mut self, id: &TypeId) -> &'a mut (dyn Column + 'static) {
+        self.try_take(id)
+            .expect("each component type can only be used once in the same query")
+    }
+
+    /// Takes out the column for the given type, or None if the archetype
+    /// doesn't have it.
+    fn try_take(&mut self, id: &TypeId) -> Option<&'a mut (dyn Column + 'static)> {
+        self.columns.remove(id)
+    }
 }
 
-/// Used to downcast a vector for dynamic Any into a vec of either simple
-/// references or mutable references. Having this in a separate trait means we
-/// can reduce the number of QueryMut implementation, as we only need an
-/// implementation for the number of item in the tuple and not on for each
-/// combination of ref and mut (e.g. (&mut, &, &), (&, &mut, &) etc.). This is
-/// because the generic type will automatically choose the correct downcast
-/// method based on the generic type that is passed in.
-pub trait Downcastable<'a> {
+/// Implemented for each type that can be part of a `QueryMut`. Having this in
+/// a separate trait means we can reduce the number of QueryMut implementation,
+/// as we only need an implementation for the number of item in the tuple and
+/// not on for each combination of ref and mut (e.g. (&mut, &, &), (&, &mut, &)
+/// etc.). This is because the generic type will automatically choose the
+/// correct fetch method based on the generic type that is passed in.
+pub trait FetchMut<'a> {
     type Item;
-    fn id() -> TypeId;
-    fn downcast(vec: &'a mut Vec<Option<Box<dyn Any>>>) -> Vec<Option<Self::Item>>;
+    type Iter: Iterator<Item = Self::Item>;
+    fn matches(archetype: &Archetype) -> bool;
+    fn fetch(columns: &mut ArchetypeColumns<'a>) -> Self::Iter;
+    /// Same as `Fetch::includes`.
+    fn includes(_archetype: &Archetype, _row: usize, _last_change_tick: u64) -> bool {
+        true
+    }
+    /// Called for each entity included in the query before fetching. We can't
+    /// know if a mutable reference is actually written to, so we assume that
+    /// it is and mark the component as changed.
+    fn mark_changed(_archetype: &mut Archetype, _row: usize, _change_tick: u64) {}
 }
 
-/// Implements downcasting to a normal reference.
-impl<'a, T: 'static> Downcastable<'a> for &T {
+/// Implements fetching a normal reference.
+impl<'a, T: 'static> FetchMut<'a> for &T {
     type Item = &'a T;
-    fn id() -> TypeId {
-        TypeId::of::<T>()
-    }
-    fn downcast(vec: &'a mut Vec<Option<Box<dyn Any>>>) -> Vec<Option<Self::Item>> {
-        vec.into_iter()
-            .map(|option| {
-                option
-                    .as_ref()
-                    .map(|value| value.downcast_ref::<T>().unwrap())
-            })
-            .collect()
+    type Iter = Iter<'a, T>;
+    fn matches(archetype: &Archetype) -> bool {
+        archetype.has(&TypeId::of::<T>())
+    }
+    fn fetch(columns: &mut ArchetypeColumns<'a>) -> Self::Iter {
+        let column: &'a dyn Column = columns.take(&TypeId::of::<T>());
+        column.as_any().downcast_ref::<Vec<T>>().unwrap().iter()
     }
 }
 
-/// Implements downcasting to a mutable reference.
-impl<'a, T: 'static> Downcastable<'a> for &mut T {
+/// Implements fetching a mutable reference.
+impl<'a, T: 'static> FetchMut<'a> for &mut T {
     type Item = &'a mut T;
-    fn id() -> TypeId {
-        TypeId::of::<T>()
-    }
-    fn downcast(vec: &'a mut Vec<Option<Box<dyn Any>>>) -> Vec<Option<Self::Item>> {
-        vec.into_iter()
-            .map(|option| {
-                option
-                    .as_mut()
-                    .map(|value| value.downcast_mut::<T>().unwrap())
-            })
-            .collect()
+    type Iter = IterMut<'a, T>;
+    fn matches(archetype: &Archetype) -> bool {
+        archetype.has(&TypeId::of::<T>())
+    }
+    fn fetch(columns: &mut ArchetypeColumns<'a>) -> Self::Iter {
+        columns
+            .take(&TypeId::of::<T>())
+            .as_any_mut()
+            .downcast_mut::<Vec<T>>()
+            .unwrap()
+            .iter_mut()
+    }
+    fn mark_changed(archetype: &mut Archetype, row: usize, change_tick: u64) {
+        archetype.ticks.get_mut(&TypeId::of::<T>()).unwrap()[row].changed = change_tick;
+    }
+}
+
+/// Implements fetching the entity handle.
+impl<'a> FetchMut<'a> for Entity {
+    type Item = Entity;
+    type Iter = Copied<Iter<'a, Entity>>;
+    fn matches(_archetype: &Archetype) -> bool {
+        true
+    }
+    fn fetch(columns: &mut ArchetypeColumns<'a>) -> Self::Iter {
+        columns.entities.iter().copied()
+    }
+}
+
+/// Implements fetching an optional normal reference.
+impl<'a, T: 'static> FetchMut<'a> for Option<&T> {
+    type Item = Option<&'a T>;
+    type Iter = OptionalIter<Iter<'a, T>>;
+    fn matches(_archetype: &Archetype) -> bool {
+        true
+    }
+    fn fetch(columns: &mut ArchetypeColumns<'a>) -> Self::Iter {
+        match columns.try_take(&TypeId::of::<T>()) {
+            Some(column) => {
+                let column: &'a (dyn Column + 'static) = column;
+                OptionalIter::Present(column.as_any().downcast_ref::<Vec<T>>().unwrap().iter())
+            }
+            None => OptionalIter::Missing(columns.entities.len()),
+        }
+    }
+}
+
+/// Implements fetching an optional mutable reference.
+impl<'a, T: 'static> FetchMut<'a> for Option<&mut T> {
+    type Item = Option<&'a mut T>;
+    type Iter = OptionalIter<IterMut<'a, T>>;
+    fn matches(_archetype: &Archetype) -> bool {
+        true
+    }
+    fn fetch(columns: &mut ArchetypeColumns<'a>) -> Self::Iter {
+        match columns.try_take(&TypeId::of::<T>()) {
+            Some(column) => OptionalIter::Present(
+                column
+                    .as_any_mut()
+                    .downcast_mut::<Vec<T>>()
+                    .unwrap()
+                    .iter_mut(),
+            ),
+            None => OptionalIter::Missing(columns.entities.len()),
+        }
+    }
+    fn mark_changed(archetype: &mut Archetype, row: usize, change_tick: u64) {
+        if let Some(ticks) = archetype.ticks.get_mut(&TypeId::of::<T>()) {
+            ticks[row].changed = change_tick;
+        }
+    }
+}
+
+/// Implements the `With` filter.
+impl<'a, T: 'static> FetchMut<'a> for With<T> {
+    type Item = ();
+    type Iter = RepeatN<()>;
+    fn matches(archetype: &Archetype) -> bool {
+        archetype.has(&TypeId::of::<T>())
+    }
+    fn fetch(columns: &mut ArchetypeColumns<'a>) -> Self::Iter {
+        repeat_n((), columns.entities.len())
+    }
+}
+
+/// Implements the `Without` filter.
+impl<'a, T: 'static> FetchMut<'a> for Without<T> {
+    type Item = ();
+    type Iter = RepeatN<()>;
+    fn matches(archetype: &Archetype) -> bool {
+        !archetype.has(&TypeId::of::<T>())
+    }
+    fn fetch(columns: &mut ArchetypeColumns<'a>) -> Self::Iter {
+        repeat_n((), columns.entities.len())
+    }
+}
+
+/// Implements the `Added` filter.
+impl<'a, T: 'static> FetchMut<'a> for Added<T> {
+    type Item = ();
+    type Iter = RepeatN<()>;
+    fn matches(archetype: &Archetype) -> bool {
+        archetype.has(&TypeId::of::<T>())
+    }
+    fn fetch(columns: &mut ArchetypeColumns<'a>) -> Self::Iter {
+        repeat_n((), columns.entities.len())
+    }
+    fn includes(archetype: &Archetype, row: usize, last_change_tick: u64) -> bool {
+        <Added<T> as Fetch>::includes(archetype, row, last_change_tick)
+    }
+}
+
+/// Implements the `Changed` filter.
+impl<'a, T: 'static> FetchMut<'a> for Changed<T> {
+    type Item = ();
+    type Iter = RepeatN<()>;
+    fn matches(archetype: &Archetype) -> bool {
+        archetype.has(&TypeId::of::<T>())
+    }
+    fn fetch(columns: &mut ArchetypeColumns<'a>) -> Self::Iter {
+        repeat_n((), columns.entities.len())
+    }
+    fn includes(archetype: &Archetype, row: usize, last_change_tick: u64) -> bool {
+        <Changed<T> as Fetch>::includes(archetype, row, last_change_tick)
     }
 }
@@ -0,0 +1,137 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::entities::Entity;
+use crate::world::World;
+
+/// Inserts a deserialized component for an entity. Loading is split in two
+/// steps so that a snapshot can be checked for errors before the world is
+/// changed.
+pub type ComponentLoader = Box<dyn FnOnce(&mut World, Entity)>;
+
+/// Inserts a deserialized resource.
+pub type ResourceLoader = Box<dyn FnOnce(&mut World)>;
+
+struct ComponentRegistration {
+    name: &'static str,
+    save: fn(&World, Entity) -> Option<Value>,
+    load: fn(Value) -> Result<ComponentLoader, serde_json::Error>,
+}
+
+struct ResourceRegistration {
+    name: &'static str,
+    save: fn(&World) -> Option<Value>,
+    load: fn(Value) -> Result<ResourceLoader, serde_json::Error>,
+}
+
+/// The world stores components and resources as `Any`, so it doesn't know how
+/// to serialize them. The registry keeps a name and a pair of functions for
+/// each type that should be part of a snapshot. Types that haven't been
+/// registered are left out, which is useful for anything that can be computed
+/// from the other components (or can't be serialized at all, like the canvas).
+pub struct Registry {
+    components: Vec<ComponentRegistration>,
+    resources: Vec<ResourceRegistration>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Registry {
+            components: vec![],
+            resources: vec![],
+        }
+    }
+
+    /// Registers a component type. The name is used as the key in snapshots,
+    /// so it shouldn't change once snapshots have been saved.
+    pub fn register_component<T>(&mut self, name: &'static str)
+    where
+        T: Serialize + DeserializeOwned + 'static,
+    {
+        self.components
+            .retain(|registration| registration.name != name);
+        self.components.push(ComponentRegistration {
+            name,
+            save: |world, entity| {
+                world.get::<T>(entity).map(|component| {
+                    serde_json::to_value(component).expect("failed to serialize component")
+                })
+            },
+            load: |value| {
+                let component: T = serde_json::from_value(value)?;
+                Ok(Box::new(move |world, entity| {
+                    world.insert_component(entity, component)
+                }))
+            },
+        });
+    }
+
+    /// Registers a resource type. See `register_component`.
+    pub fn register_resource<T>(&mut self, name: &'static str)
+    where
+        T: Serialize + DeserializeOwned + 'static,
+    {
+        self.resources
+            .retain(|registration| registration.name != name);
+        self.resources.push(ResourceRegistration {
+            name,
+            save: |world| {
+                world.get_resource::<T>().map(|resource| {
+                    serde_json::to_value(&*resource).expect("failed to serialize resource")
+                })
+            },
+            load: |value| {
+                let resource: T = serde_json::from_value(value)?;
+                Ok(Box::new(move |world| world.create_resource(resource)))
+            },
+        });
+    }
+
+    /// Serializes every registered component of the given entity, by name.
+    pub fn save_components(&self, world: &World, entity: Entity) -> Map<String, Value> {
+        self.components
+            .iter()
+            .filter_map(|registration| {
+                (registration.save)(world, entity)
+                    .map(|value| (registration.name.to_string(), value))
+            })
+            .collect()
+    }
+
+    /// Serializes every registered resource in the world, by name.
+    pub fn save_resources(&self, world: &World) -> Map<String, Value> {
+        self.resources
+            .iter()
+            .filter_map(|registration| {
+                (registration.save)(world).map(|value| (registration.name.to_string(), value))
+            })
+            .collect()
+    }
+
+    pub fn load_component(&self, name: &str, value: Value) -> Result<ComponentLoader, String> {
+        let registration = self
+            .components
+            .iter()
+            .find(|registration| registration.name == name)
+            .ok_or_else(|| format!("unknown component `{}`", name))?;
+        (registration.load)(value)
+            .map_err(|error| format!("invalid component `{}`: {}", name, error))
+    }
+
+    pub fn load_resource(&self, name: &str, value: Value) -> Result<ResourceLoader, String> {
+        let registration = self
+            .resources
+            .iter()
+            .find(|registration| registration.name == name)
+            .ok_or_else(|| format!("unknown resource `{}`", name))?;
+        (registration.load)(value)
+            .map_err(|error| format!("invalid resource `{}`: {}", name, error))
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
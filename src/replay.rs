@@ -0,0 +1,373 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::components::program::Program;
+use crate::resources::input::{Input, KeyCode};
+use crate::resources::player::Player;
+use crate::resources::time::Time;
+use crate::scene::Scene;
+use crate::scenes::loader;
+use crate::world::World;
+
+// A run only depends on the scene it started from, the random seed, and what
+// the player did at which step. Recording those is enough to reproduce a run
+// step by step, no matter how often frames were rendered when it was recorded.
+
+/// Something the player did that can change how a run plays out.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ReplayInput {
+    ChangeProgram { source: String },
+    KeyDown { key: String },
+    KeyUp { key: String },
+    SetPaused { paused: bool },
+    SetTimeScale { scale: f32 },
+    LoadState { state: String },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RecordedInput {
+    /// The number of steps that had run when the input happened. It takes
+    /// effect before the next step.
+    pub tick: u64,
+    #[serde(flatten)]
+    pub input: ReplayInput,
+}
+
+/// Everything needed to play a run again, along with a snapshot of how it
+/// ended so that the replay can be checked against it.
+#[derive(Serialize, Deserialize)]
+pub struct Replay {
+    /// The scene file the run started from.
+    pub scene: String,
+    pub seed: u64,
+    pub inputs: Vec<RecordedInput>,
+    /// The number of steps that had run when the recording ended.
+    pub ticks: u64,
+    /// A snapshot of the world when the recording ended. It's kept as the
+    /// text from `World::snapshot` so that it can be compared exactly.
+    pub snapshot: String,
+}
+
+/// Keeps track of the inputs of a run while it is being played.
+pub struct Recorder {
+    scene: String,
+    seed: u64,
+    inputs: Vec<RecordedInput>,
+}
+
+/// Loads a playable scene with a seeded random number generator, the way
+/// every recorded run starts out.
+pub fn load_world(scene: &str, seed: u64) -> Result<World, String> {
+    loader::load_playable_world(scene, seed)
+}
+
+/// Applies an input to the world. Inputs should always go through here, both
+/// when playing and replaying, so that they have the exact same effect.
+pub fn apply_input(world: &mut World, input: &ReplayInput) -> Result<(), String> {
+    match input {
+        ReplayInput::ChangeProgram { source } => {
+            let player = match world.get_resource::<Player>() {
+                Some(player) => player.entity,
+                None => return Err(String::from("there is no player")),
+            };
+            match world.get_mut::<Program>(player) {
+                Some(program) => program.update(source.clone()),
+                None => return Err(String::from("the player has no program")),
+            }
+        }
+        ReplayInput::KeyDown { key } => {
+            if let (Some(key_code), Some(mut input)) =
+                (KeyCode::new(key), world.get_resource_mut::<Input>())
+            {
+                input.press(key_code);
+            }
+        }
+        ReplayInput::KeyUp { key } => {
+            if let (Some(key_code), Some(mut input)) =
+                (KeyCode::new(key), world.get_resource_mut::<Input>())
+            {
+                input.release(key_code);
+            }
+        }
+        ReplayInput::SetPaused { paused } => {
+            if let Some(mut time) = world.get_resource_mut::<Time>() {
+                time.paused = *paused;
+            }
+        }
+        ReplayInput::SetTimeScale { scale } => {
+            if let Some(mut time) = world.get_resource_mut::<Time>() {
+                time.set_scale(*scale)?;
+            }
+        }
+        ReplayInput::LoadState { state } => world.restore(state)?,
+    }
+    Ok(())
+}
+
+impl Recorder {
+    /// Starts recording a run of the given scene file, loaded with
+    /// `load_world`.
+    pub fn new(scene: &str, seed: u64) -> Self {
+        Recorder {
+            scene: scene.to_string(),
+            seed,
+            inputs: vec![],
+        }
+    }
+
+    /// Records an input that has been applied to the world.
+    pub fn record(&mut self, world: &World, input: ReplayInput) {
+        let tick = world.get_resource::<Time>().map_or(0, |time| time.ticks());
+        self.inputs.push(RecordedInput { tick, input });
+    }
+
+    /// Returns a replay of the run so far.
+    pub fn finish(&self, world: &World) -> Replay {
+        Replay {
+            scene: self.scene.clone(),
+            seed: self.seed,
+            inputs: self.inputs.clone(),
+            ticks: world.get_resource::<Time>().map_or(0, |time| time.ticks()),
+            snapshot: world.snapshot(),
+        }
+    }
+}
+
+impl Replay {
+    pub fn from_json(text: &str) -> Result<Replay, String> {
+        serde_json::from_str(text).map_err(|error| error.to_string())
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    /// Creates the world the run started out with. See `load_world`.
+    pub fn load_world(&self) -> Result<World, String> {
+        load_world(&self.scene, self.seed)
+    }
+
+    /// Plays the recorded inputs on a scene created from `load_world`, one
+    /// step at a time, until it has run as many steps as the recording.
+    /// Returns an error if it doesn't end up in the recorded state.
+    pub fn play(&self, scene: &mut Scene) -> Result<(), String> {
+        let mut inputs = self.inputs.iter().peekable();
+        loop {
+            // Loading a state can move time backwards, so the current tick
+            // has to be checked again after every input.
+            let tick = scene.world.get_resource::<Time>().unwrap().ticks();
+            if let Some(recorded) = inputs.next_if(|recorded| recorded.tick <= tick) {
+                apply_input(&mut scene.world, &recorded.input).map_err(|error| {
+                    format!("couldn't replay input at tick {}: {}", recorded.tick, error)
+                })?;
+                continue;
+            }
+            if inputs.peek().is_none() && tick >= self.ticks {
+                break;
+            }
+            scene.step();
+        }
+
+        match difference(
+            &comparable(&self.snapshot)?,
+            &comparable(&scene.world.snapshot())?,
+        ) {
+            Some(difference) => Err(format!(
+                "the replay diverged from the recording: {}",
+                difference
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Continues recording from the end of the replay.
+    pub fn into_recorder(self) -> Recorder {
+        Recorder {
+            scene: self.scene,
+            seed: self.seed,
+            inputs: self.inputs,
+        }
+    }
+}
+
+// The camera moves on every frame rather than on every step, so it depends on
+// how often the game was rendered. It's left out when comparing runs.
+fn comparable(snapshot: &str) -> Result<Value, String> {
+    let mut snapshot: Value = serde_json::from_str(snapshot).map_err(|error| error.to_string())?;
+    if let Some(resources) = snapshot.get_mut("resources").and_then(Value::as_object_mut) {
+        resources.remove("Viewport");
+    }
+    Ok(snapshot)
+}
+
+// Describes the first difference between two snapshots, if there is one.
+fn difference(expected: &Value, actual: &Value) -> Option<String> {
+    if expected == actual {
+        return None;
+    }
+
+    let empty = vec![];
+    let expected_entities = expected["entities"].as_array().unwrap_or(&empty);
+    let actual_entities = actual["entities"].as_array().unwrap_or(&empty);
+    if expected_entities.len() != actual_entities.len() {
+        return Some(format!(
+            "expected {} entities, found {}",
+            expected_entities.len(),
+            actual_entities.len()
+        ));
+    }
+    for (expected, actual) in expected_entities.iter().zip(actual_entities) {
+        if expected["entity"] != actual["entity"] {
+            return Some(format!("expected entity {}", expected["entity"]));
+        }
+        if let Some(name) = first_different_key(&expected["components"], &actual["components"]) {
+            return Some(format!(
+                "component `{}` of entity {} is different",
+                name, expected["entity"]
+            ));
+        }
+    }
+
+    match first_different_key(&expected["resources"], &actual["resources"]) {
+        Some(name) => Some(format!("resource `{}` is different", name)),
+        None => Some(String::from("the snapshots are different")),
+    }
+}
+
+fn first_different_key(expected: &Value, actual: &Value) -> Option<String> {
+    let empty = serde_json::Map::new();
+    let expected = expected.as_object().unwrap_or(&empty);
+    let actual = actual.as_object().unwrap_or(&empty);
+    expected
+        .keys()
+        .chain(actual.keys())
+        .find(|key| expected.get(*key) != actual.get(*key))
+        .cloned()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::scenes::{create_scene, SceneRegistry};
+
+    const PROGRAM: &str = "
+        IF TIME > 500 DO
+          SET_THRUST(1.0)
+        END
+        IF ANGLE > 0 DO
+          SET_TORQUE(200)
+        END
+    ";
+
+    // Plays a scene the way the game does, with uneven frames and inputs
+    // happening between them.
+    fn record() -> (Replay, Scene) {
+        let text = SceneRegistry::built_in()
+            .get("landing")
+            .unwrap()
+            .to_string();
+        let mut scene = create_scene(load_world(&text, 7).unwrap());
+        let mut recorder = Recorder::new(&text, 7);
+        let mut input = |scene: &mut Scene, input: ReplayInput| {
+            apply_input(&mut scene.world, &input).unwrap();
+            recorder.record(&scene.world, input);
+        };
+
+        input(
+            &mut scene,
+            ReplayInput::ChangeProgram {
+                source: String::from(PROGRAM),
+            },
+        );
+        let frames = [0.016, 0.033, 0.007, 0.05, 0.021];
+        for (frame, frame_time) in frames.iter().cycle().take(120).enumerate() {
+            scene.update(*frame_time);
+            match frame {
+                20 => input(&mut scene, ReplayInput::SetTimeScale { scale: 4.0 }),
+                40 => input(
+                    &mut scene,
+                    ReplayInput::KeyDown {
+                        key: String::from("w"),
+                    },
+                ),
+                60 => input(&mut scene, ReplayInput::SetPaused { paused: true }),
+                70 => input(
+                    &mut scene,
+                    ReplayInput::ChangeProgram {
+                        source: String::from("SET_TORQUE(-100)"),
+                    },
+                ),
+                80 => input(&mut scene, ReplayInput::SetPaused { paused: false }),
+                _ => {}
+            }
+        }
+
+        (recorder.finish(&scene.world), scene)
+    }
+
+    #[test]
+    fn test_replay_matches_recording() {
+        let (replay, scene) = record();
+        assert!(replay.ticks > 100);
+        assert_eq!(replay.inputs.len(), 6);
+
+        let replay = Replay::from_json(&replay.to_json()).unwrap();
+        let mut replayed = create_scene(replay.load_world().unwrap());
+        assert_eq!(replay.play(&mut replayed), Ok(()));
+        assert_eq!(
+            replayed.world.get_resource::<Time>().unwrap().ticks(),
+            scene.world.get_resource::<Time>().unwrap().ticks()
+        );
+    }
+
+    #[test]
+    fn test_replay_with_loaded_state() {
+        let registry = SceneRegistry::built_in();
+        let text = registry.get("rendezvous").unwrap();
+        let mut scene = create_scene(load_world(text, 1).unwrap());
+        let mut recorder = Recorder::new(text, 1);
+
+        for _ in 0..30 {
+            scene.step();
+        }
+        let state = scene.world.snapshot();
+        for _ in 0..30 {
+            scene.step();
+        }
+        let input = ReplayInput::LoadState { state };
+        apply_input(&mut scene.world, &input).unwrap();
+        recorder.record(&scene.world, input);
+        for _ in 0..10 {
+            scene.step();
+        }
+
+        let replay = recorder.finish(&scene.world);
+        assert_eq!(replay.ticks, 40);
+        let mut replayed = create_scene(replay.load_world().unwrap());
+        assert_eq!(replay.play(&mut replayed), Ok(()));
+    }
+
+    #[test]
+    fn test_invalid_time_scale() {
+        let registry = SceneRegistry::built_in();
+        let mut scene = create_scene(load_world(registry.get("rendezvous").unwrap(), 1).unwrap());
+        let input = ReplayInput::SetTimeScale { scale: -2.0 };
+        assert!(apply_input(&mut scene.world, &input).is_err());
+        assert_eq!(scene.world.get_resource::<Time>().unwrap().scale(), 1.0);
+    }
+
+    #[test]
+    fn test_replay_detects_divergence() {
+        let (mut replay, _) = record();
+        replay.inputs.remove(0);
+
+        let mut replayed = create_scene(replay.load_world().unwrap());
+        let error = replay.play(&mut replayed).unwrap_err();
+        assert!(
+            error.starts_with("the replay diverged from the recording: component"),
+            "{}",
+            error
+        );
+    }
+}
@@ -1,7 +1,9 @@
 use crate::components::point::Point;
 use crate::components::shape::ColorRGBA;
-use std::cell::RefCell;
-use std::rc::Rc;
+#[cfg(test)]
+use std::any::Any;
+use std::ops::{Deref, DerefMut};
+#[cfg(feature = "web")]
 use wasm_bindgen::JsCast;
 
 pub struct Path {
@@ -19,17 +21,68 @@ impl Path {
     }
 }
 
-// Exposes method on CanvasRenderingContext2d, so that we can use the
-// "interior mutability" pattern. This allows us to borrow the
-// resource immutably, but still mutate to the underlying context.
-// TODO: Use RefCell on world.get_resource method instead?
+// The drawing operations used by the renderers. In the browser they are
+// forwarded to a CanvasRenderingContext2d, while headless builds either ignore
+// them or record them.
+pub trait CanvasBackend {
+    fn width(&self) -> f64;
+    fn height(&self) -> f64;
+    fn draw_rectangle(&mut self, x: f32, y: f32, width: f32, height: f32, color: &ColorRGBA);
+    fn draw_text(&mut self, x: f32, y: f32, font: &str, text: &str, color: &ColorRGBA);
+    fn draw_path(&mut self, path: Path, width: f32, color: &ColorRGBA);
+    fn clear_rect(&mut self, x: f64, y: f64, w: f64, h: f64);
+    fn translate(&mut self, x: f64, y: f64);
+    fn rotate(&mut self, angle: f64);
+    fn reset_transform(&mut self);
+    // Allows getting the concrete backend back, see `Canvas::backend`.
+    #[cfg(test)]
+    fn as_any(&self) -> &dyn Any;
+}
+
+// The canvas resource the renderers draw to. Drawing requires a mutable borrow
+// of the resource, using `world.get_resource_mut`. All the drawing methods
+// come from the backend.
 pub struct Canvas {
-    context: Rc<RefCell<web_sys::CanvasRenderingContext2d>>,
-    element: web_sys::HtmlCanvasElement,
+    backend: Box<dyn CanvasBackend>,
 }
 
 impl Canvas {
-    pub fn new() -> Canvas {
+    pub fn new<B: CanvasBackend + 'static>(backend: B) -> Canvas {
+        Canvas {
+            backend: Box::new(backend),
+        }
+    }
+
+    /// Returns the backend, if it is of the given type.
+    #[cfg(test)]
+    pub fn backend<B: CanvasBackend + 'static>(&self) -> Option<&B> {
+        self.backend.as_any().downcast_ref()
+    }
+}
+
+impl Deref for Canvas {
+    type Target = dyn CanvasBackend;
+    fn deref(&self) -> &Self::Target {
+        self.backend.as_ref()
+    }
+}
+
+impl DerefMut for Canvas {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.backend.as_mut()
+    }
+}
+
+// Draws to the `<canvas id="canvas">` element on the page.
+#[cfg(feature = "web")]
+pub struct WebCanvas {
+    context: web_sys::CanvasRenderingContext2d,
+    element: web_sys::HtmlCanvasElement,
+}
+
+#[cfg(feature = "web")]
+impl WebCanvas {
+    pub fn new() -> WebCanvas {
         let window = web_sys::window().expect("no global `window` exists");
         let document: web_sys::Document =
             window.document().expect("should have a document on window");
@@ -46,35 +99,35 @@ impl Canvas {
             .dyn_into::<web_sys::CanvasRenderingContext2d>()
             .unwrap();
 
-        Canvas {
-            context: Rc::new(RefCell::new(context)),
-            element,
-        }
+        WebCanvas { context, element }
     }
+}
 
-    pub fn width(&self) -> f64 {
+#[cfg(feature = "web")]
+impl CanvasBackend for WebCanvas {
+    fn width(&self) -> f64 {
         self.element.width() as f64
     }
 
-    pub fn height(&self) -> f64 {
+    fn height(&self) -> f64 {
         self.element.height() as f64
     }
 
-    pub fn draw_rectangle(&self, x: f32, y: f32, width: f32, height: f32, color: &ColorRGBA) {
-        let context = self.context.borrow_mut();
+    fn draw_rectangle(&mut self, x: f32, y: f32, width: f32, height: f32, color: &ColorRGBA) {
+        let context = &self.context;
         context.set_fill_style_str(&color.to_string());
         context.fill_rect(x as f64, y as f64, width as f64, height as f64);
     }
 
-    pub fn draw_text(&self, x: f32, y: f32, font: &str, text: &str, color: &ColorRGBA) {
-        let context = self.context.borrow_mut();
+    fn draw_text(&mut self, x: f32, y: f32, font: &str, text: &str, color: &ColorRGBA) {
+        let context = &self.context;
         context.set_font(font);
         context.set_stroke_style_str(&color.to_string());
         context.fill_text(text, x as f64, y as f64).unwrap();
     }
 
-    pub fn draw_path(&self, path: Path, width: f32, color: &ColorRGBA) {
-        let context = self.context.borrow_mut();
+    fn draw_path(&mut self, path: Path, width: f32, color: &ColorRGBA) {
+        let context = &self.context;
         context.begin_path();
         context.set_line_width(width as f64);
         context.set_stroke_style_str(&color.to_string());
@@ -88,23 +141,211 @@ impl Canvas {
         context.close_path();
     }
 
-    pub fn clear_rect(&self, x: f64, y: f64, w: f64, h: f64) {
-        let context = self.context.borrow_mut();
+    fn clear_rect(&mut self, x: f64, y: f64, w: f64, h: f64) {
+        let context = &self.context;
         context.clear_rect(x, y, w, h);
     }
 
-    pub fn translate(&self, x: f64, y: f64) {
-        let context = self.context.borrow_mut();
+    fn translate(&mut self, x: f64, y: f64) {
+        let context = &self.context;
         context.translate(x, y).unwrap();
     }
 
-    pub fn rotate(&self, angle: f64) {
-        let context = self.context.borrow_mut();
+    fn rotate(&mut self, angle: f64) {
+        let context = &self.context;
         context.rotate(angle).unwrap();
     }
 
-    pub fn reset_transform(&self) {
-        let context = self.context.borrow_mut();
+    fn reset_transform(&mut self) {
+        let context = &self.context;
         context.reset_transform().unwrap();
     }
+
+    #[cfg(test)]
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+// Ignores everything drawn to it. Used when running without a browser.
+pub struct NullCanvas {
+    width: f64,
+    height: f64,
+}
+
+impl NullCanvas {
+    pub fn new(width: f64, height: f64) -> NullCanvas {
+        NullCanvas { width, height }
+    }
+}
+
+impl CanvasBackend for NullCanvas {
+    fn width(&self) -> f64 {
+        self.width
+    }
+
+    fn height(&self) -> f64 {
+        self.height
+    }
+
+    fn draw_rectangle(&mut self, _: f32, _: f32, _: f32, _: f32, _: &ColorRGBA) {}
+    fn draw_text(&mut self, _: f32, _: f32, _: &str, _: &str, _: &ColorRGBA) {}
+    fn draw_path(&mut self, _: Path, _: f32, _: &ColorRGBA) {}
+    fn clear_rect(&mut self, _: f64, _: f64, _: f64, _: f64) {}
+    fn translate(&mut self, _: f64, _: f64) {}
+    fn rotate(&mut self, _: f64) {}
+    fn reset_transform(&mut self) {}
+
+    #[cfg(test)]
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// A single call to one of the drawing methods, as recorded by
+/// `RecordingCanvas`. Colors are stored in their CSS form.
+#[cfg(test)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum DrawCommand {
+    Rectangle {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        color: String,
+    },
+    Text {
+        x: f32,
+        y: f32,
+        text: String,
+        color: String,
+    },
+    Path {
+        points: Vec<(f32, f32)>,
+        width: f32,
+        color: String,
+    },
+    Clear,
+    Translate {
+        x: f64,
+        y: f64,
+    },
+    Rotate {
+        angle: f64,
+    },
+    ResetTransform,
+}
+
+// Keeps a list of everything drawn to it, so that tests can check what the
+// renderers draw.
+#[cfg(test)]
+pub struct RecordingCanvas {
+    width: f64,
+    height: f64,
+    pub commands: Vec<DrawCommand>,
+}
+
+#[cfg(test)]
+impl RecordingCanvas {
+    pub fn new(width: f64, height: f64) -> RecordingCanvas {
+        RecordingCanvas {
+            width,
+            height,
+            commands: vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+impl CanvasBackend for RecordingCanvas {
+    fn width(&self) -> f64 {
+        self.width
+    }
+
+    fn height(&self) -> f64 {
+        self.height
+    }
+
+    fn draw_rectangle(&mut self, x: f32, y: f32, width: f32, height: f32, color: &ColorRGBA) {
+        self.commands.push(DrawCommand::Rectangle {
+            x,
+            y,
+            width,
+            height,
+            color: color.to_string(),
+        });
+    }
+
+    fn draw_text(&mut self, x: f32, y: f32, _font: &str, text: &str, color: &ColorRGBA) {
+        self.commands.push(DrawCommand::Text {
+            x,
+            y,
+            text: text.to_string(),
+            color: color.to_string(),
+        });
+    }
+
+    fn draw_path(&mut self, path: Path, width: f32, color: &ColorRGBA) {
+        self.commands.push(DrawCommand::Path {
+            points: path.points.iter().map(|point| (point.x, point.y)).collect(),
+            width,
+            color: color.to_string(),
+        });
+    }
+
+    fn clear_rect(&mut self, _: f64, _: f64, _: f64, _: f64) {
+        self.commands.push(DrawCommand::Clear);
+    }
+
+    fn translate(&mut self, x: f64, y: f64) {
+        self.commands.push(DrawCommand::Translate { x, y });
+    }
+
+    fn rotate(&mut self, angle: f64) {
+        self.commands.push(DrawCommand::Rotate { angle });
+    }
+
+    fn reset_transform(&mut self) {
+        self.commands.push(DrawCommand::ResetTransform);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_recording_canvas() {
+        let mut canvas = Canvas::new(RecordingCanvas::new(800.0, 600.0));
+        let color = ColorRGBA {
+            r: 255,
+            g: 0,
+            b: 0,
+            a: 1.0,
+        };
+
+        assert_eq!(canvas.width(), 800.0);
+        canvas.translate(10.0, 20.0);
+        let mut path = Path::new();
+        path.line_to(0.0, 0.0).line_to(1.0, 1.0);
+        canvas.draw_path(path, 2.0, &color);
+
+        let recording = canvas.backend::<RecordingCanvas>().unwrap();
+        assert_eq!(
+            recording.commands,
+            vec![
+                DrawCommand::Translate { x: 10.0, y: 20.0 },
+                DrawCommand::Path {
+                    points: vec![(0.0, 0.0), (1.0, 1.0)],
+                    width: 2.0,
+                    color: String::from("rgba(255, 0, 0, 1)"),
+                },
+            ]
+        );
+        assert!(canvas.backend::<NullCanvas>().is_none());
+    }
 }
@@ -1,3 +1,6 @@
 pub mod canvas;
 pub mod input;
+pub mod player;
+pub mod random;
+pub mod time;
 pub mod viewport;
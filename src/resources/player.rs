@@ -0,0 +1,9 @@
+use crate::entities::Entity;
+use serde::{Deserialize, Serialize};
+
+// Keeps track of the ship controlled by the player, so that we can update its
+// program without having to search through every entity.
+#[derive(Serialize, Deserialize)]
+pub struct Player {
+    pub entity: Entity,
+}
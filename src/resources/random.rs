@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+
+// A seeded random number generator (SplitMix64). Systems should use this
+// rather than any other source of randomness, so that a run can be reproduced
+// from its seed. The state is part of snapshots.
+#[derive(Serialize, Deserialize)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Random { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_gives_same_numbers() {
+        let mut a = Random::new(42);
+        let mut b = Random::new(42);
+        let mut c = Random::new(43);
+
+        let numbers: Vec<u64> = (0..3).map(|_| a.next_u64()).collect();
+        assert_eq!(numbers, (0..3).map(|_| b.next_u64()).collect::<Vec<_>>());
+        assert_ne!(numbers, (0..3).map(|_| c.next_u64()).collect::<Vec<_>>());
+    }
+}
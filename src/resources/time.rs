@@ -0,0 +1,189 @@
+use crate::world::World;
+use serde::{Deserialize, Serialize};
+
+// The simulation runs in fixed steps, independent of how often the browser
+// calls us. Real time is added to an accumulator, and we run one simulation
+// step for every whole step that fits in it. Whatever is left over is used to
+// interpolate between the last two steps when rendering.
+//
+// Fast-forwarding doesn't change the length of a step, we just run more of
+// them per frame. That way a program behaves the same at any speed.
+#[derive(Serialize, Deserialize)]
+pub struct Time {
+    /// The length of a single simulation step, in seconds.
+    pub step: f32,
+    scale: f32,
+    /// Time stands still while paused, but the scene is still rendered.
+    pub paused: bool,
+    ticks: u64,
+    // This depends on how often frames were rendered, so it's left out of
+    // snapshots. Restoring one starts at the beginning of a step.
+    #[serde(skip)]
+    accumulator: f32,
+}
+
+// If the tab has been in the background for a while we don't want to catch up
+// on all the missed steps at once, so we limit how much time a single frame
+// can add (before scaling).
+const MAX_FRAME_TIME: f32 = 0.25;
+
+impl Time {
+    pub fn new(step: f32) -> Self {
+        Time {
+            step,
+            scale: 1.0,
+            paused: false,
+            ticks: 0,
+            accumulator: 0.0,
+        }
+    }
+
+    /// Adds the real time since the last frame, in seconds.
+    pub fn advance(&mut self, frame_time: f32) {
+        if !self.paused {
+            self.accumulator += frame_time.clamp(0.0, MAX_FRAME_TIME) * self.scale;
+        }
+    }
+
+    /// Returns true if there is enough accumulated time to run another
+    /// simulation step, and moves the elapsed time forward.
+    pub fn consume_step(&mut self) -> bool {
+        if self.accumulator >= self.step {
+            self.accumulator -= self.step;
+            self.ticks += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Moves the elapsed time forward by a single step, without using any
+    /// accumulated time. Used to run the simulation as fast as possible.
+    pub fn force_step(&mut self) {
+        self.ticks += 1;
+    }
+
+    /// How fast the simulation runs compared to real time, e.g. 2.0 to run at
+    /// double speed.
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// Changes how fast the simulation runs. A scale that isn't a finite,
+    /// positive number (or zero) is rejected, since it would break the
+    /// accumulator.
+    pub fn set_scale(&mut self, scale: f32) -> Result<(), String> {
+        if !scale.is_finite() || scale < 0.0 {
+            return Err(format!("invalid time scale {}", scale));
+        }
+        self.scale = scale;
+        Ok(())
+    }
+
+    /// The number of simulation steps that have run so far.
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// The total simulated time, in seconds. This only moves forward in whole
+    /// steps, so it's the same on every machine.
+    pub fn elapsed(&self) -> f64 {
+        self.ticks as f64 * self.step as f64
+    }
+
+    /// How far we are between the last simulation step and the next one, from
+    /// 0.0 to 1.0.
+    pub fn alpha(&self) -> f32 {
+        self.accumulator / self.step
+    }
+}
+
+impl Default for Time {
+    fn default() -> Self {
+        Self::new(1.0 / 60.0)
+    }
+}
+
+/// Run condition for systems that should stop while the game is paused.
+pub fn not_paused(world: &World) -> bool {
+    match world.get_resource::<Time>() {
+        Some(time) => !time.paused,
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_consume_step() {
+        let mut time = Time::new(0.125);
+        time.advance(0.1);
+        assert!(!time.consume_step());
+
+        time.advance(0.1);
+        assert!(time.consume_step());
+        assert!(!time.consume_step());
+        assert_eq!(time.ticks(), 1);
+        assert_eq!(time.elapsed(), 0.125);
+        assert!((time.alpha() - 0.6).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_advance_is_limited() {
+        let mut time = Time::new(0.1);
+        time.advance(10.0);
+
+        let mut steps = 0;
+        while time.consume_step() {
+            steps += 1;
+        }
+        assert_eq!(steps, 2);
+    }
+
+    #[test]
+    fn test_paused() {
+        let mut time = Time::new(0.125);
+        time.paused = true;
+        time.advance(0.25);
+
+        assert!(!time.consume_step());
+        assert_eq!(time.alpha(), 0.0);
+        assert_eq!(time.elapsed(), 0.0);
+    }
+
+    #[test]
+    fn test_scale() {
+        let mut time = Time::new(0.125);
+        time.set_scale(2.0).unwrap();
+        time.advance(0.125);
+        assert_eq!(time.alpha(), 2.0);
+
+        let mut steps = 0;
+        while time.consume_step() {
+            steps += 1;
+        }
+        assert_eq!(steps, 2);
+        assert_eq!(time.ticks(), 2);
+
+        time.set_scale(0.5).unwrap();
+        time.advance(0.125);
+        assert!(!time.consume_step());
+        time.advance(0.125);
+        assert!(time.consume_step());
+    }
+
+    #[test]
+    fn test_invalid_scale() {
+        let mut time = Time::new(0.125);
+        assert!(time.set_scale(f32::NAN).is_err());
+        assert!(time.set_scale(f32::INFINITY).is_err());
+        assert!(time.set_scale(-1.0).is_err());
+        assert_eq!(time.scale(), 1.0);
+
+        assert!(time.set_scale(0.0).is_ok());
+        time.advance(0.25);
+        assert!(!time.consume_step());
+    }
+}
@@ -1,5 +1,7 @@
 use rapier2d::na::Vector2;
+use serde::{Deserialize, Serialize};
 
+#[derive(Serialize, Deserialize)]
 pub struct Viewport {
     pub position: Vector2<f32>,
     pub target_position: Vector2<f32>,
@@ -1,12 +1,195 @@
-use crate::{systems::System, world::World};
+use crate::resources::time::Time;
+use crate::schedule::{Schedule, Stage};
+use crate::world::World;
 
 pub struct Scene {
     pub world: World,
-    pub systems: Vec<Box<dyn System>>,
+    pub schedule: Schedule,
 }
 
 impl Scene {
-    pub fn new(world: World, systems: Vec<Box<dyn System>>) -> Scene {
-        Scene { world, systems }
+    pub fn new(mut world: World, schedule: Schedule) -> Scene {
+        if world.get_resource::<Time>().is_none() {
+            world.create_resource(Time::default());
+        }
+        Scene { world, schedule }
+    }
+
+    /// Moves the scene forward by the given real time, in seconds. The input
+    /// and render stages run once for every call, while the logic and physics
+    /// stages run once for every fixed simulation step that has passed. Any
+    /// commands recorded by a system are applied before the next system runs,
+    /// so it will see the changes.
+    pub fn update(&mut self, frame_time: f32) {
+        // Events live for two frames, so drop the ones sent before the last
+        // frame.
+        self.world.update_events();
+
+        // Time stands still while the game is paused, so this won't add
+        // anything to the accumulator then.
+        self.world
+            .get_resource_mut::<Time>()
+            .unwrap()
+            .advance(frame_time);
+
+        self.schedule.run_stage(Stage::Input, &mut self.world);
+
+        while self
+            .world
+            .get_resource_mut::<Time>()
+            .unwrap()
+            .consume_step()
+        {
+            self.schedule.run_stage(Stage::Logic, &mut self.world);
+            self.schedule.run_stage(Stage::Physics, &mut self.world);
+        }
+
+        self.schedule.run_stage(Stage::Render, &mut self.world);
+    }
+
+    /// Runs a single simulation step right away, regardless of real time and
+    /// the time scale. Nothing is rendered.
+    pub fn step(&mut self) {
+        self.world.update_events();
+        self.world.get_resource_mut::<Time>().unwrap().force_step();
+        self.schedule.run_stage(Stage::Logic, &mut self.world);
+        self.schedule.run_stage(Stage::Physics, &mut self.world);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::entities::Entity;
+    use crate::systems::System;
+
+    struct Debris;
+
+    struct DespawnSystem {}
+
+    impl System for DespawnSystem {
+        fn update(&mut self, world: &mut World) {
+            for (entity, _) in world.query::<(Entity, &Debris)>() {
+                world.commands().despawn(entity);
+            }
+        }
+    }
+
+    struct CountSystem {
+        count: usize,
+    }
+
+    impl System for CountSystem {
+        fn update(&mut self, world: &mut World) {
+            self.count = world.query::<&Debris>().len();
+            world.create_resource(self.count);
+        }
+    }
+
+    struct StepCounter {}
+
+    impl System for StepCounter {
+        fn update(&mut self, world: &mut World) {
+            *world.get_resource_mut::<usize>().unwrap() += 1;
+        }
+    }
+
+    struct FrameCounter {}
+
+    impl System for FrameCounter {
+        fn update(&mut self, world: &mut World) {
+            let frames = world.get_resource::<u8>().map_or(0, |frames| *frames);
+            world.create_resource(frames + 1);
+        }
+    }
+
+    #[test]
+    fn test_update_applies_commands_between_systems() {
+        let mut world = World::new();
+        world.create_entity().with_component(Debris);
+        world.create_entity().with_component(Debris);
+
+        let mut schedule = Schedule::new();
+        schedule.add_system(Stage::Logic, DespawnSystem {});
+        schedule.add_system(Stage::Logic, CountSystem { count: 2 });
+
+        let mut scene = Scene::new(world, schedule);
+        scene.update(1.0);
+
+        assert_eq!(scene.world.get_resource::<usize>().as_deref(), Some(&0));
+    }
+
+    #[test]
+    fn test_update_runs_fixed_steps() {
+        let mut world = World::new();
+        world.create_resource(Time::new(0.125));
+        world.create_resource(0_usize);
+
+        let mut schedule = Schedule::new();
+        schedule.add_system(Stage::Physics, StepCounter {});
+        schedule.add_system(Stage::Render, FrameCounter {});
+        let mut scene = Scene::new(world, schedule);
+
+        scene.update(0.25);
+        assert_eq!(scene.world.get_resource::<usize>().as_deref(), Some(&2));
+        assert_eq!(scene.world.get_resource::<u8>().as_deref(), Some(&1));
+
+        scene.update(0.125);
+        assert_eq!(scene.world.get_resource::<usize>().as_deref(), Some(&3));
+        assert_eq!(scene.world.get_resource::<u8>().as_deref(), Some(&2));
+
+        scene.world.get_resource_mut::<Time>().unwrap().paused = true;
+        scene.update(0.25);
+        assert_eq!(scene.world.get_resource::<usize>().as_deref(), Some(&3));
+        assert_eq!(scene.world.get_resource::<u8>().as_deref(), Some(&3));
+    }
+
+    struct RemovedDebris {
+        removed: Vec<Entity>,
+    }
+
+    impl System for RemovedDebris {
+        fn update(&mut self, world: &mut World) {
+            self.removed = world.removed_components::<Debris>();
+        }
+    }
+
+    #[test]
+    fn test_step_clears_removed_components() {
+        let mut world = World::new();
+        world.create_entity().with_component(Debris);
+        world.create_entity().with_component(Debris);
+
+        // The input stage never runs when stepping
+        let mut schedule = Schedule::new();
+        schedule.add_system(Stage::Input, FrameCounter {});
+        schedule.add_system(Stage::Logic, DespawnSystem {});
+        let mut scene = Scene::new(world, schedule);
+
+        scene.step();
+        scene.step();
+
+        // A system that has never run sees everything that is still kept
+        let mut system = RemovedDebris { removed: vec![] };
+        scene.world.run_system(&mut system, &mut 0);
+        assert!(system.removed.is_empty());
+    }
+
+    #[test]
+    fn test_step() {
+        let mut world = World::new();
+        world.create_resource(Time::new(0.125));
+        world.create_resource(0_usize);
+
+        let mut schedule = Schedule::new();
+        schedule.add_system(Stage::Physics, StepCounter {});
+        schedule.add_system(Stage::Render, FrameCounter {});
+        let mut scene = Scene::new(world, schedule);
+
+        scene.step();
+        scene.step();
+        assert_eq!(scene.world.get_resource::<usize>().as_deref(), Some(&2));
+        assert_eq!(scene.world.get_resource::<u8>().as_deref(), None);
+        assert_eq!(scene.world.get_resource::<Time>().unwrap().ticks(), 2);
     }
 }
@@ -0,0 +1,428 @@
+use hashbrown::{HashMap, HashSet};
+use rapier2d::na::Vector2;
+use serde::Deserialize;
+
+use crate::components::computer::Computer;
+use crate::components::gravity::GravitySource;
+use crate::components::hierarchy::{LocalTransform, Parent};
+use crate::components::point::Point;
+use crate::components::program::Program;
+use crate::components::rigid_body::{PhysicsMode, RigidBody, Transform};
+use crate::components::shape::{ColorRGBA, Polygon, Shape};
+use crate::components::thrusters::{Thruster, Thrusters};
+use crate::entities::Entity;
+use crate::resources::player::Player;
+use crate::resources::random::Random;
+use crate::resources::viewport::Viewport;
+use crate::scenes::register_types;
+use crate::world::World;
+
+// Scenes are described in JSON, so that they can be written without touching
+// any Rust code. Every entity is a list of optional components, and vectors are
+// written as `[x, y]`. See `sandbox.json` for an example.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneDefinition {
+    viewport: Option<ViewportDefinition>,
+    entities: Vec<EntityDefinition>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ViewportDefinition {
+    position: [f32; 2],
+    zoom: f32,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EntityDefinition {
+    // Used to refer to the entity as a parent
+    name: Option<String>,
+    // The entity controlled by the player's program. Only one entity can be
+    // the player.
+    #[serde(default)]
+    player: bool,
+    parent: Option<String>,
+    local_transform: Option<TransformDefinition>,
+    rigid_body: Option<RigidBodyDefinition>,
+    shape: Option<ShapeDefinition>,
+    gravity_source: Option<GravitySource>,
+    thrusters: Option<ThrustersDefinition>,
+    program: Option<Program>,
+    computer: Option<Computer>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TransformDefinition {
+    position: [f32; 2],
+    #[serde(default)]
+    rotation: f32,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RigidBodyDefinition {
+    position: [f32; 2],
+    #[serde(default)]
+    rotation: f32,
+    #[serde(default = "default_mass")]
+    mass: f32,
+    #[serde(default)]
+    linear_velocity: [f32; 2],
+    #[serde(default)]
+    angular_velocity: f32,
+    #[serde(default = "default_physics_mode")]
+    physics_mode: PhysicsMode,
+}
+
+fn default_mass() -> f32 {
+    1.0
+}
+
+fn default_physics_mode() -> PhysicsMode {
+    PhysicsMode::Dynamic
+}
+
+#[derive(Deserialize)]
+struct ShapeDefinition {
+    #[serde(flatten)]
+    geometry: GeometryDefinition,
+    color: ColorRGBA,
+    #[serde(default)]
+    is_sensor: bool,
+}
+
+// A shape is either a list of vertices, or the parameters passed to one of the
+// `Polygon` generators. Planetoids without a noise seed get one from the
+// scene's random number generator.
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum GeometryDefinition {
+    Vertices(Vec<[f32; 2]>),
+    Planetoid {
+        radius: f32,
+        resolution: i32,
+        noise_seed: Option<i32>,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ThrustersDefinition {
+    fuel: f64,
+    fuel_max: f64,
+    thrusters: Vec<Thruster>,
+}
+
+impl From<TransformDefinition> for Transform {
+    fn from(definition: TransformDefinition) -> Self {
+        Transform {
+            position: Vector2::new(definition.position[0], definition.position[1]),
+            rotation: definition.rotation,
+        }
+    }
+}
+
+impl From<RigidBodyDefinition> for RigidBody {
+    fn from(definition: RigidBodyDefinition) -> Self {
+        RigidBody {
+            transform: Transform {
+                position: Vector2::new(definition.position[0], definition.position[1]),
+                rotation: definition.rotation,
+            },
+            mass: definition.mass,
+            linear_velocity: Vector2::new(
+                definition.linear_velocity[0],
+                definition.linear_velocity[1],
+            ),
+            angular_velocity: definition.angular_velocity,
+            physics_mode: definition.physics_mode,
+        }
+    }
+}
+
+impl ShapeDefinition {
+    fn into_shape(self, random: &mut Random) -> Shape {
+        let vertices = match self.geometry {
+            GeometryDefinition::Vertices(vertices) => {
+                vertices.into_iter().map(|[x, y]| Point { x, y }).collect()
+            }
+            GeometryDefinition::Planetoid {
+                radius,
+                resolution,
+                noise_seed,
+            } => {
+                // The noise overflows with large seeds, so they're kept small
+                let noise_seed = noise_seed.unwrap_or_else(|| (random.next_u64() % 1000) as i32);
+                Polygon::planetoid(radius, resolution, noise_seed)
+            }
+        };
+
+        Shape {
+            vertices,
+            color: self.color,
+            is_sensor: self.is_sensor,
+        }
+    }
+}
+
+/// Creates a world with the entities and resources described by the given
+/// scene file, with a random number generator that starts out with the given
+/// seed. Anything random in the scene is generated from it.
+pub fn load_world(text: &str, seed: u64) -> Result<World, String> {
+    let definition: SceneDefinition =
+        serde_json::from_str(text).map_err(|error| error.to_string())?;
+
+    let mut world = World::new();
+    register_types(&mut world);
+    let mut random = Random::new(seed);
+
+    if let Some(viewport) = definition.viewport {
+        let position = Vector2::new(viewport.position[0], viewport.position[1]);
+        world.create_resource(Viewport {
+            position,
+            zoom: viewport.zoom,
+            target_position: position,
+            target_zoom: viewport.zoom,
+        });
+    }
+
+    // Parents are looked up by name once every entity has been created, so
+    // that a child can be listed before its parent.
+    let mut names: HashMap<String, Entity> = HashMap::new();
+    let mut parents: Vec<(Entity, String)> = vec![];
+    let mut player = None;
+
+    for entity_definition in definition.entities {
+        let entity = world.create_entity().entity();
+
+        if let Some(name) = entity_definition.name {
+            if names.insert(name.clone(), entity).is_some() {
+                return Err(format!("there are multiple entities named `{}`", name));
+            }
+        }
+        if entity_definition.player {
+            if player.is_some() {
+                return Err(String::from("only one entity can be the player"));
+            }
+            player = Some(entity);
+        }
+        if let Some(parent) = entity_definition.parent {
+            parents.push((entity, parent));
+        }
+        if let Some(local_transform) = entity_definition.local_transform {
+            world.insert_component(entity, LocalTransform(local_transform.into()));
+        }
+        if let Some(rigid_body) = entity_definition.rigid_body {
+            world.insert_component(entity, RigidBody::from(rigid_body));
+        }
+        if let Some(shape) = entity_definition.shape {
+            world.insert_component(entity, shape.into_shape(&mut random));
+        }
+        if let Some(gravity_source) = entity_definition.gravity_source {
+            world.insert_component(entity, gravity_source);
+        }
+        if let Some(thrusters) = entity_definition.thrusters {
+            world.insert_component(
+                entity,
+                Thrusters::new(thrusters.fuel, thrusters.fuel_max, thrusters.thrusters),
+            );
+        }
+        if let Some(program) = entity_definition.program {
+            world.insert_component(entity, program);
+        }
+        if let Some(computer) = entity_definition.computer {
+            world.insert_component(entity, computer);
+        }
+    }
+
+    let mut resolved: HashMap<Entity, Entity> = HashMap::new();
+    for (entity, parent) in &parents {
+        match names.get(parent) {
+            Some(parent) => resolved.insert(*entity, *parent),
+            None => return Err(format!("there is no entity named `{}`", parent)),
+        };
+    }
+
+    // Only named entities can be parents, so every entity in a cycle has a
+    // name to report.
+    for (entity, parent) in &parents {
+        if resolved[entity] == *entity {
+            return Err(format!("entity `{}` is its own parent", parent));
+        }
+        let mut visited = HashSet::new();
+        let mut current = resolved[entity];
+        while let Some(next) = resolved.get(&current) {
+            if *next == *entity {
+                return Err(format!("the parents of `{}` form a cycle", parent));
+            }
+            // A cycle further up is reported when its own entities are checked
+            if !visited.insert(current) {
+                break;
+            }
+            current = *next;
+        }
+    }
+
+    for (entity, parent) in resolved {
+        world.insert_component(entity, Parent(parent));
+    }
+
+    if let Some(entity) = player {
+        world.create_resource(Player { entity });
+    }
+    world.create_resource(random);
+
+    Ok(world)
+}
+
+/// Like `load_world`, but for scenes that are controlled by the
+/// player's program, which need a player entity with a program.
+pub fn load_playable_world(text: &str, seed: u64) -> Result<World, String> {
+    let world = load_world(text, seed)?;
+    let has_program = match world.get_resource::<Player>() {
+        Some(player) => world.has::<Program>(player.entity),
+        None => false,
+    };
+    if has_program {
+        Ok(world)
+    } else {
+        Err(String::from(
+            "the scene needs a player entity with a program",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_load_world() {
+        let world = load_world(include_str!("sandbox.json"), 0).unwrap();
+
+        assert_eq!(world.query::<&RigidBody>().len(), 5);
+        assert_eq!(world.query::<&GravitySource>().len(), 2);
+        assert_eq!(world.get_resource::<Viewport>().unwrap().zoom, 0.2);
+
+        let player = world.get_resource::<Player>().unwrap().entity;
+        assert!(world.has::<Program>(player));
+        assert!(world.has::<Thrusters>(player));
+        assert_eq!(world.get::<Shape>(player).unwrap().vertices.len(), 4);
+
+        // The planetoids are generated from their parameters
+        let planets = world.query::<(&Shape, &GravitySource)>();
+        let mut resolutions: Vec<usize> = planets
+            .iter()
+            .map(|(shape, _)| shape.vertices.len())
+            .collect();
+        resolutions.sort();
+        assert_eq!(resolutions, vec![32, 64]);
+    }
+
+    #[test]
+    fn test_planetoids_without_noise_seed() {
+        let text = r#"{
+            "entities": [{
+                "shape": {
+                    "planetoid": { "radius": 100.0, "resolution": 16 },
+                    "color": { "r": 255, "g": 255, "b": 255, "a": 1.0 }
+                }
+            }]
+        }"#;
+        let vertices = |seed| {
+            let world = load_world(text, seed).unwrap();
+            let shape = world.query::<&Shape>()[0];
+            shape
+                .vertices
+                .iter()
+                .map(|point| (point.x, point.y))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(vertices(1), vertices(1));
+        assert_ne!(vertices(1), vertices(2));
+    }
+
+    #[test]
+    fn test_load_world_with_defaults_and_parents() {
+        let world = load_world(
+            r#"{
+                "entities": [
+                    {
+                        "name": "leg",
+                        "parent": "ship",
+                        "local_transform": { "position": [10.0, 0.0] }
+                    },
+                    {
+                        "name": "ship",
+                        "rigid_body": { "position": [1.0, 2.0] },
+                        "program": "SET_THRUST(1.0)",
+                        "computer": { "cycles": 500 }
+                    }
+                ]
+            }"#,
+            0,
+        )
+        .unwrap();
+
+        let (leg, parent) = world.query::<(Entity, &Parent)>()[0];
+        let (ship, rigid_body) = world.query::<(Entity, &RigidBody)>()[0];
+        assert_eq!(parent.0, ship);
+        assert_eq!(rigid_body.mass, 1.0);
+        assert_eq!(rigid_body.transform.rotation, 0.0);
+        assert!(matches!(rigid_body.physics_mode, PhysicsMode::Dynamic));
+        assert_eq!(
+            world.get::<LocalTransform>(leg).unwrap().0.position,
+            Vector2::new(10.0, 0.0)
+        );
+        assert!(world.get::<Program>(ship).unwrap().program.is_ok());
+        assert_eq!(world.get::<Computer>(ship).unwrap().cycles, 500);
+        assert!(world.get_resource::<Player>().is_none());
+    }
+
+    #[test]
+    fn test_load_world_errors() {
+        assert!(load_world("{", 0).is_err());
+        assert!(load_world(r#"{ "entities": [{ "mass": 1.0 }] }"#, 0).is_err());
+        assert_eq!(
+            load_world(r#"{ "entities": [{ "parent": "ship" }] }"#, 0).err(),
+            Some(String::from("there is no entity named `ship`"))
+        );
+        assert_eq!(
+            load_world(
+                r#"{ "entities": [{ "player": true }, { "player": true }] }"#,
+                0
+            )
+            .err(),
+            Some(String::from("only one entity can be the player"))
+        );
+        assert_eq!(
+            load_world(
+                r#"{ "entities": [{ "name": "ship", "parent": "ship" }] }"#,
+                0
+            )
+            .err(),
+            Some(String::from("entity `ship` is its own parent"))
+        );
+        assert_eq!(
+            load_world(
+                r#"{ "entities": [
+                    { "name": "moon", "parent": "a" },
+                    { "name": "a", "parent": "b" },
+                    { "name": "b", "parent": "a" }
+                ] }"#,
+                0
+            )
+            .err(),
+            Some(String::from("the parents of `b` form a cycle"))
+        );
+        assert_eq!(
+            load_playable_world(r#"{ "entities": [{ "player": true }] }"#, 0).err(),
+            Some(String::from(
+                "the scene needs a player entity with a program"
+            ))
+        );
+    }
+}
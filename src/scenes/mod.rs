@@ -1 +1,234 @@
-pub mod scene_1;
+use rapier2d::na::Vector2;
+
+use crate::components::computer::Computer;
+use crate::components::gravity::GravitySource;
+use crate::components::hierarchy::{LocalTransform, Parent};
+use crate::components::program::Program;
+use crate::components::rigid_body::RigidBody;
+use crate::components::shape::Shape;
+use crate::components::thrusters::Thrusters;
+use crate::resources::canvas::{Canvas, NullCanvas};
+use crate::resources::input::Input;
+use crate::resources::player::Player;
+use crate::resources::random::Random;
+use crate::resources::time::{not_paused, Time};
+use crate::resources::viewport::Viewport;
+use crate::scene::Scene;
+use crate::schedule::{Schedule, Stage};
+use crate::systems::instruments_renderer::InstrumentsRenderer;
+use crate::systems::interpreter::{InterpreterSystem, ProgramErrored};
+use crate::systems::scene_renderer::SceneRenderer;
+use crate::systems::simulation::{CollisionStarted, SimulationSystem};
+use crate::systems::thrust::ThrusterSystem;
+use crate::systems::transform_propagation::TransformPropagationSystem;
+use crate::systems::viewport::ViewportSystem;
+use crate::world::World;
+
+pub mod loader;
+
+/// Keeps track of the scene files that can be played, by name, in the order
+/// they were registered.
+pub struct SceneRegistry {
+    scenes: Vec<(String, String)>,
+}
+
+impl SceneRegistry {
+    pub fn new() -> Self {
+        SceneRegistry { scenes: vec![] }
+    }
+
+    /// Returns a registry with the scenes that ship with the game. The first
+    /// one is the scene the game starts with.
+    pub fn built_in() -> Self {
+        let mut registry = SceneRegistry::new();
+        registry.register("tutorial", include_str!("tutorial.json"));
+        registry.register("landing", include_str!("landing.json"));
+        registry.register("rendezvous", include_str!("rendezvous.json"));
+        registry.register("sandbox", include_str!("sandbox.json"));
+        registry
+    }
+
+    /// Adds a scene file, replacing any scene with the same name.
+    pub fn register(&mut self, name: &str, text: &str) {
+        match self.scenes.iter_mut().find(|(other, _)| other == name) {
+            Some((_, existing)) => *existing = text.to_string(),
+            None => self.scenes.push((name.to_string(), text.to_string())),
+        }
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.scenes.iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    /// Returns the scene file with the given name.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.scenes
+            .iter()
+            .find(|(other, _)| other == name)
+            .map(|(_, text)| text.as_str())
+    }
+}
+
+impl Default for SceneRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Registers the components and resources that are saved in snapshots. Any
+/// other state is either computed from these, or tied to the browser.
+pub fn register_types(world: &mut World) {
+    world.register_component::<RigidBody>("RigidBody");
+    world.register_component::<Shape>("Shape");
+    world.register_component::<GravitySource>("GravitySource");
+    world.register_component::<Thrusters>("Thrusters");
+    world.register_component::<Program>("Program");
+    world.register_component::<Computer>("Computer");
+    world.register_component::<Parent>("Parent");
+    world.register_component::<LocalTransform>("LocalTransform");
+
+    world.register_resource::<Time>("Time");
+    world.register_resource::<Viewport>("Viewport");
+    world.register_resource::<Player>("Player");
+    world.register_resource::<Random>("Random");
+}
+
+/// Adds the resources that every scene needs, along with the systems that run
+/// the game, to a world describing the contents of the scene.
+pub fn create_scene(mut world: World) -> Scene {
+    // Rendering canvas resource. The web frontend adds one that draws to the
+    // page, anywhere else the scene is rendered to a canvas that ignores it.
+    if world.get_resource::<Canvas>().is_none() {
+        world.create_resource(Canvas::new(NullCanvas::new(800.0, 600.0)));
+    }
+
+    // Input event handler
+    world.create_resource(Input::default());
+
+    // Simulation time, which can be paused and sped up from the frontend
+    if world.get_resource::<Time>().is_none() {
+        world.create_resource(Time::default());
+    }
+
+    // Scenes are seeded when they are loaded by the game, so that a run can be
+    // replayed. Anywhere else they always start out with the same seed.
+    if world.get_resource::<Random>().is_none() {
+        world.create_resource(Random::new(0));
+    }
+
+    // Scenes that don't set a viewport start out looking at the origin
+    if world.get_resource::<Viewport>().is_none() {
+        world.create_resource(Viewport {
+            position: Vector2::new(0.0, 0.0),
+            zoom: 1.0,
+            target_position: Vector2::new(0.0, 0.0),
+            target_zoom: 1.0,
+        });
+    }
+
+    // Events that any system can subscribe to
+    world.add_event::<CollisionStarted>();
+    world.add_event::<ProgramErrored>();
+
+    let mut schedule = Schedule::new();
+    schedule.add_system(Stage::Input, ViewportSystem::new());
+    schedule
+        .add_system(Stage::Logic, InterpreterSystem::new())
+        .label("interpreter")
+        .run_if(not_paused);
+    schedule
+        .add_system(Stage::Logic, ThrusterSystem::new())
+        .after("interpreter")
+        .run_if(not_paused);
+    schedule
+        .add_system(Stage::Physics, SimulationSystem::new())
+        .label("simulation")
+        .run_if(not_paused);
+    schedule
+        .add_system(Stage::Physics, TransformPropagationSystem::new())
+        .after("simulation")
+        .run_if(not_paused);
+    // The instruments renderer clears the canvas, so it needs to run first
+    schedule
+        .add_system(Stage::Render, InstrumentsRenderer::new())
+        .before("scene");
+    schedule
+        .add_system(Stage::Render, SceneRenderer::new())
+        .label("scene");
+
+    Scene::new(world, schedule)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::components::rigid_body::RigidBody;
+    use crate::resources::canvas::{DrawCommand, RecordingCanvas};
+
+    #[test]
+    fn test_register() {
+        let mut registry = SceneRegistry::new();
+        registry.register("first", "1");
+        registry.register("second", "2");
+        registry.register("first", "3");
+
+        assert_eq!(registry.names(), vec!["first", "second"]);
+        assert_eq!(registry.get("first"), Some("3"));
+        assert_eq!(registry.get("third"), None);
+    }
+
+    #[test]
+    fn test_built_in_scenes_are_valid() {
+        let registry = SceneRegistry::built_in();
+        for name in registry.names() {
+            let world = loader::load_world(registry.get(name).unwrap(), 0)
+                .unwrap_or_else(|error| panic!("scene `{}` is invalid: {}", name, error));
+            assert!(world.get_resource::<Player>().is_some(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_run_scene_natively() {
+        let world = loader::load_world(include_str!("sandbox.json"), 0).unwrap();
+        let mut scene = create_scene(world);
+        let player = scene.world.get_resource::<Player>().unwrap().entity;
+        let start = scene
+            .world
+            .get::<RigidBody>(player)
+            .unwrap()
+            .transform
+            .position;
+
+        for _ in 0..60 {
+            scene.update(1.0 / 60.0);
+        }
+
+        assert!(scene.world.get_resource::<Time>().unwrap().ticks() > 0);
+        assert_ne!(
+            scene
+                .world
+                .get::<RigidBody>(player)
+                .unwrap()
+                .transform
+                .position,
+            start
+        );
+    }
+
+    #[test]
+    fn test_render_to_recording_canvas() {
+        let mut world = loader::load_world(include_str!("sandbox.json"), 0).unwrap();
+        world.create_resource(Canvas::new(RecordingCanvas::new(800.0, 600.0)));
+        let mut scene = create_scene(world);
+        scene.update(1.0 / 60.0);
+
+        let canvas = scene.world.get_resource::<Canvas>().unwrap();
+        let commands = &canvas.backend::<RecordingCanvas>().unwrap().commands;
+        assert_eq!(commands[0], DrawCommand::Clear);
+        let paths = commands
+            .iter()
+            .filter(|command| matches!(command, DrawCommand::Path { .. }))
+            .count();
+        assert_eq!(paths, scene.world.query::<&Shape>().len());
+    }
+}
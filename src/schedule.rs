@@ -0,0 +1,278 @@
+use crate::systems::System;
+use crate::world::World;
+
+/// The stages of a single tick, in the order they run. Every system in a
+/// stage runs before any system in the next stage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Stage {
+    Input,
+    Logic,
+    Physics,
+    Render,
+}
+
+/// A run condition decides if a system should run this tick, for instance to
+/// skip the simulation while the game is paused.
+pub type RunCondition = fn(&World) -> bool;
+
+/// A system in the schedule, along with everything needed to decide when it
+/// should run. Returned by `Schedule::add_system` so that the ordering
+/// constraints and run conditions can be chained.
+pub struct SystemEntry {
+    system: Box<dyn System>,
+    stage: Stage,
+    labels: Vec<&'static str>,
+    before: Vec<&'static str>,
+    after: Vec<&'static str>,
+    conditions: Vec<RunCondition>,
+    // The tick the system last ran at, used for change detection.
+    last_run: u64,
+}
+
+impl SystemEntry {
+    /// Adds a label that other systems can refer to in `before` and `after`.
+    pub fn label(&mut self, label: &'static str) -> &mut Self {
+        self.labels.push(label);
+        self
+    }
+
+    /// Runs this system before any system with the given label.
+    pub fn before(&mut self, label: &'static str) -> &mut Self {
+        self.before.push(label);
+        self
+    }
+
+    /// Runs this system after any system with the given label.
+    pub fn after(&mut self, label: &'static str) -> &mut Self {
+        self.after.push(label);
+        self
+    }
+
+    /// Only runs the system when the condition returns true. If there are
+    /// multiple conditions, all of them need to return true.
+    pub fn run_if(&mut self, condition: RunCondition) -> &mut Self {
+        self.conditions.push(condition);
+        self
+    }
+
+    fn should_run(&self, world: &World) -> bool {
+        self.conditions.iter().all(|condition| condition(world))
+    }
+
+    // Returns true if this system has to run before the other one. Ordering
+    // constraints only apply between systems in the same stage.
+    fn runs_before(&self, other: &SystemEntry) -> bool {
+        self.stage == other.stage
+            && (self.before.iter().any(|label| other.labels.contains(label))
+                || other.after.iter().any(|label| self.labels.contains(label)))
+    }
+}
+
+/// Decides the order the systems in a scene run in. Systems are grouped into
+/// stages, and within each stage they run in the order they were added unless
+/// there are any `before` or `after` constraints.
+pub struct Schedule {
+    systems: Vec<SystemEntry>,
+    // Indices into `systems` in the order they should run. Cleared every time
+    // a system is added, and sorted again the next time the schedule runs.
+    order: Option<Vec<usize>>,
+}
+
+impl Schedule {
+    pub fn new() -> Self {
+        Schedule {
+            systems: vec![],
+            order: None,
+        }
+    }
+
+    /// Adds a system to the given stage.
+    pub fn add_system<S: System + 'static>(&mut self, stage: Stage, system: S) -> &mut SystemEntry {
+        self.order = None;
+        self.systems.push(SystemEntry {
+            system: Box::new(system),
+            stage,
+            labels: vec![],
+            before: vec![],
+            after: vec![],
+            conditions: vec![],
+            last_run: 0,
+        });
+        self.systems.last_mut().unwrap()
+    }
+
+    /// Runs the systems in a single stage whose run conditions are met. The
+    /// scene decides how often each stage runs, see `Scene::update`.
+    pub fn run_stage(&mut self, stage: Stage, world: &mut World) {
+        if self.order.is_none() {
+            self.order = Some(self.sort());
+        }
+
+        for index in self.order.as_ref().unwrap() {
+            let entry = &mut self.systems[*index];
+            if entry.stage == stage && entry.should_run(world) {
+                world.run_system(entry.system.as_mut(), &mut entry.last_run);
+            }
+        }
+
+        // Every system has now seen the components removed before the oldest
+        // tick, so we don't need to keep them around. Systems that have never
+        // run (like the render stage when only stepping the simulation, or
+        // anything paused since the start) would otherwise keep them forever.
+        if let Some(oldest) = self
+            .systems
+            .iter()
+            .map(|entry| entry.last_run)
+            .filter(|last_run| *last_run > 0)
+            .min()
+        {
+            world.clear_removed_components(oldest);
+        }
+    }
+
+    // Sorts the systems by stage, and then by their ordering constraints. We
+    // repeatedly pick the first system (in the order they were added) that
+    // doesn't have to wait for any of the remaining systems.
+    fn sort(&self) -> Vec<usize> {
+        let mut remaining: Vec<usize> = (0..self.systems.len()).collect();
+        remaining.sort_by_key(|index| self.systems[*index].stage);
+
+        let mut order = vec![];
+        while !remaining.is_empty() {
+            let next = remaining
+                .iter()
+                .position(|index| {
+                    let entry = &self.systems[*index];
+                    remaining.iter().all(|other| {
+                        let other = &self.systems[*other];
+                        other.stage >= entry.stage && !other.runs_before(entry)
+                    })
+                })
+                .expect("the system ordering constraints contain a cycle");
+            order.push(remaining.remove(next));
+        }
+        order
+    }
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Appends its name to a shared log resource every time it runs.
+    struct LogSystem {
+        name: &'static str,
+    }
+
+    impl System for LogSystem {
+        fn update(&mut self, world: &mut World) {
+            let mut log = world.get_resource_mut::<Vec<&'static str>>().unwrap();
+            log.push(self.name);
+        }
+    }
+
+    fn run(schedule: &mut Schedule) -> Vec<&'static str> {
+        let mut world = World::new();
+        world.create_resource::<Vec<&'static str>>(vec![]);
+        for stage in [Stage::Input, Stage::Logic, Stage::Physics, Stage::Render] {
+            schedule.run_stage(stage, &mut world);
+        }
+        let log = world.get_resource::<Vec<&'static str>>().unwrap().clone();
+        log
+    }
+
+    #[test]
+    fn test_stages() {
+        let mut schedule = Schedule::new();
+        schedule.add_system(Stage::Render, LogSystem { name: "render" });
+        schedule.add_system(Stage::Physics, LogSystem { name: "physics" });
+        schedule.add_system(Stage::Input, LogSystem { name: "input" });
+        schedule.add_system(Stage::Logic, LogSystem { name: "logic" });
+
+        assert_eq!(
+            run(&mut schedule),
+            vec!["input", "logic", "physics", "render"]
+        );
+    }
+
+    #[test]
+    fn test_insertion_order_within_stage() {
+        let mut schedule = Schedule::new();
+        schedule.add_system(Stage::Logic, LogSystem { name: "first" });
+        schedule.add_system(Stage::Logic, LogSystem { name: "second" });
+        schedule.add_system(Stage::Logic, LogSystem { name: "third" });
+
+        assert_eq!(run(&mut schedule), vec!["first", "second", "third"]);
+    }
+
+    #[test]
+    fn test_before_and_after() {
+        let mut schedule = Schedule::new();
+        schedule
+            .add_system(Stage::Logic, LogSystem { name: "first" })
+            .after("second");
+        schedule
+            .add_system(Stage::Logic, LogSystem { name: "second" })
+            .label("second")
+            .after("third");
+        schedule
+            .add_system(Stage::Logic, LogSystem { name: "third" })
+            .label("third");
+        schedule
+            .add_system(Stage::Logic, LogSystem { name: "fourth" })
+            .before("third");
+
+        assert_eq!(
+            run(&mut schedule),
+            vec!["fourth", "third", "second", "first"]
+        );
+    }
+
+    #[test]
+    fn test_ordering_ignores_other_stages() {
+        let mut schedule = Schedule::new();
+        schedule
+            .add_system(Stage::Logic, LogSystem { name: "logic" })
+            .after("render");
+        schedule
+            .add_system(Stage::Render, LogSystem { name: "render" })
+            .label("render");
+
+        assert_eq!(run(&mut schedule), vec!["logic", "render"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "the system ordering constraints contain a cycle")]
+    fn test_cycle() {
+        let mut schedule = Schedule::new();
+        schedule
+            .add_system(Stage::Logic, LogSystem { name: "first" })
+            .label("first")
+            .after("second");
+        schedule
+            .add_system(Stage::Logic, LogSystem { name: "second" })
+            .label("second")
+            .after("first");
+
+        run(&mut schedule);
+    }
+
+    #[test]
+    fn test_run_if() {
+        let mut schedule = Schedule::new();
+        schedule
+            .add_system(Stage::Logic, LogSystem { name: "never" })
+            .run_if(|_| false);
+        schedule
+            .add_system(Stage::Logic, LogSystem { name: "always" })
+            .run_if(|_| true);
+
+        assert_eq!(run(&mut schedule), vec!["always"]);
+    }
+}
@@ -15,7 +15,7 @@ impl InstrumentsRenderer {
 
 impl System for InstrumentsRenderer {
     fn update(&mut self, world: &mut World) {
-        let canvas = world.get_resource::<Canvas>().unwrap();
+        let mut canvas = world.get_resource_mut::<Canvas>().unwrap();
         let font = "12px monospace";
         let color = ColorRGBA {
             r: 0,
@@ -23,7 +23,8 @@ impl System for InstrumentsRenderer {
             b: 0,
             a: 1.0,
         };
-        canvas.clear_rect(0.0, 0.0, canvas.width(), canvas.height());
+        let (width, height) = (canvas.width(), canvas.height());
+        canvas.clear_rect(0.0, 0.0, width, height);
 
         for program in world.query::<&Program>() {
             let mut y = 50.0;
@@ -51,6 +52,9 @@ impl System for InstrumentsRenderer {
                     ProgramVariable::Boolean(false) => {
                         canvas.draw_text(variable_value_x, y, font, "false", &color);
                     }
+                    ProgramVariable::String(value) => {
+                        canvas.draw_text(variable_value_x, y, font, &value, &color);
+                    }
                 }
 
                 y += spacing;
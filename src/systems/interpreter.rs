@@ -1,14 +1,27 @@
 use rapier2d::na::Vector2;
 
+use crate::components::computer::Computer;
 use crate::components::gravity::GravitySource;
 use crate::components::program::Program;
 use crate::components::rigid_body::RigidBody;
+use crate::entities::Entity;
+use crate::events::Events;
 use crate::helpers::math::{angle_between_positions, delta_angle};
 use crate::interpreter::evaluator::Evaluator;
-use crate::interpreter::object::{Command, Object};
+use crate::interpreter::object::{Command, Object, RuntimeError};
+use crate::query::With;
+use crate::resources::time::Time;
 use crate::systems::System;
 use crate::world::World;
 
+/// Sent when a program runs into a runtime error. Only sent once for the same
+/// error, and not again until the program has run without it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProgramErrored {
+    pub entity: Entity,
+    pub error: RuntimeError,
+}
+
 pub struct InterpreterSystem {}
 
 impl InterpreterSystem {
@@ -22,18 +35,27 @@ impl System for InterpreterSystem {
         // These are later used by rigid bodies to determine where
         // the closest gravity source is, i.e. what direction is "down"
         let gravity_sources: Vec<Vector2<f32>> = world
-            .query_mut::<(&GravitySource, &RigidBody)>()
+            .query::<(&RigidBody, With<GravitySource>)>()
             .iter()
-            .map(|(_, rb)| rb.transform.position)
+            .map(|(rb, _)| rb.transform.position)
             .collect();
 
-        // Set current frame mission time
-        let mission_time = world.start_timestamp.elapsed().as_millis();
+        // Set current mission time in milliseconds. This is the simulated
+        // time, so it's the same on every machine.
+        let mission_time = match world.get_resource::<Time>() {
+            Some(time) => (time.elapsed() * 1000.0).round(),
+            None => 0.0,
+        };
+
+        let mut errors = vec![];
 
-        for (program, rigid_body) in world.query_mut::<(&mut Program, &RigidBody)>() {
+        for (entity, program, rigid_body, computer) in
+            world.query_mut::<(Entity, &mut Program, &RigidBody, Option<&Computer>)>()
+        {
             match &program.program {
                 Ok(parsed_program) => {
-                    let mut evaluator = Evaluator::new();
+                    let computer = computer.copied().unwrap_or_default();
+                    let mut evaluator = Evaluator::with_cycles(computer.cycles);
 
                     let closest_gravity_source =
                         get_closest_gravity_source(rigid_body, &gravity_sources);
@@ -92,22 +114,40 @@ impl System for InterpreterSystem {
                         },
                     );
 
+                    program.environment.set(
+                        String::from("LOG"),
+                        Object::Command {
+                            function: |arguments| {
+                                let message: Vec<String> = arguments
+                                    .iter()
+                                    .map(|argument| argument.to_string())
+                                    .collect();
+                                Result::Ok(Command::Log {
+                                    message: message.join(" "),
+                                })
+                            },
+                        },
+                    );
+
                     // --- TIME ---
                     program
                         .environment
                         .set(String::from("TIME"), Object::Integer(mission_time as isize));
 
-                    // --- ALTITUDE ---
-                    program.environment.set(
-                        String::from("ALTITUDE"),
-                        Object::Float(closest_gravity_source.distance as f64),
-                    );
-
-                    // --- ANGLE ---
-                    program.environment.set(
-                        String::from("ANGLE"),
-                        Object::Float((closest_gravity_source.relative_angle * 57.2958) as f64), // multiply to convert radians to deg
-                    );
+                    // Scenes without gravity sources have no ALTITUDE or ANGLE
+                    if let Some(closest_gravity_source) = closest_gravity_source {
+                        // --- ALTITUDE ---
+                        program.environment.set(
+                            String::from("ALTITUDE"),
+                            Object::Float(closest_gravity_source.distance as f64),
+                        );
+
+                        // --- ANGLE ---
+                        program.environment.set(
+                            String::from("ANGLE"),
+                            Object::Float((closest_gravity_source.relative_angle * 57.2958) as f64), // multiply to convert radians to deg
+                        );
+                    }
 
                     // --- ANG_VEL ---
                     program.environment.set(
@@ -117,27 +157,45 @@ impl System for InterpreterSystem {
 
                     let result = evaluator.eval(&parsed_program, &mut program.environment);
                     if let Object::Error(error) = result {
+                        if program.error.as_ref() != Some(&error) {
+                            errors.push(ProgramErrored {
+                                entity,
+                                error: error.clone(),
+                            });
+                        }
                         program.error = Some(error);
                     } else {
                         program.error = None;
                     }
+                    for command in &evaluator.commands {
+                        if let Command::Log { message } = command {
+                            program.console.log(message.clone());
+                        }
+                    }
                     program.commands = evaluator.commands;
                 }
                 Err(_) => break,
             }
         }
+
+        if let Some(mut events) = world.get_resource_mut::<Events<ProgramErrored>>() {
+            for error in errors {
+                events.send(error);
+            }
+        }
     }
 }
 
-struct ClosestGravitySourceParameters {
-    distance: f32,
-    relative_angle: f32,
+pub(crate) struct ClosestGravitySourceParameters {
+    pub distance: f32,
+    pub relative_angle: f32,
 }
 
-fn get_closest_gravity_source(
+// Returns None if there are no gravity sources
+pub(crate) fn get_closest_gravity_source(
     rigid_body: &RigidBody,
     gravity_sources: &Vec<Vector2<f32>>,
-) -> ClosestGravitySourceParameters {
+) -> Option<ClosestGravitySourceParameters> {
     let mut closest_gravity_position = None;
     let mut closest_gravity_distance = f32::INFINITY;
 
@@ -149,18 +207,16 @@ fn get_closest_gravity_source(
         }
     }
 
-    let angle_to_closest_gravity_source = angle_between_positions(
-        rigid_body.transform.position,
-        *closest_gravity_position.unwrap(),
-    );
+    let angle_to_closest_gravity_source =
+        angle_between_positions(rigid_body.transform.position, *closest_gravity_position?);
 
     let relative_angle = delta_angle(
         rigid_body.transform.rotation,
         angle_to_closest_gravity_source,
     );
 
-    ClosestGravitySourceParameters {
+    Some(ClosestGravitySourceParameters {
         distance: closest_gravity_distance,
         relative_angle,
-    }
+    })
 }
@@ -3,6 +3,7 @@ pub mod interpreter;
 pub mod scene_renderer;
 pub mod simulation;
 pub mod thrust;
+pub mod transform_propagation;
 pub mod viewport;
 
 use crate::world::World;
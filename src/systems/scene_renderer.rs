@@ -1,6 +1,9 @@
-use crate::components::rigid_body::RigidBody;
+use crate::components::hierarchy::{GlobalTransform, LocalTransform, Parent};
+use crate::components::rigid_body::{PreviousTransform, RigidBody, Transform};
 use crate::components::shape::Shape;
+use crate::entities::Entity;
 use crate::resources::canvas::{Canvas, Path};
+use crate::resources::time::Time;
 use crate::resources::viewport::Viewport;
 use crate::systems::System;
 use crate::world::World;
@@ -18,15 +21,24 @@ impl System for SceneRenderer {
         let viewport = world.get_resource::<Viewport>().unwrap();
         let zoom = viewport.zoom;
         let position = viewport.position;
-        let canvas = world.get_resource::<Canvas>().unwrap();
+        let mut canvas = world.get_resource_mut::<Canvas>().unwrap();
         let screen_height = canvas.height();
         let screen_width = canvas.width();
 
         // Let line width be adaptive to zoom (but min 2.0)
         let line_width = f32::max(zoom * 2.0, 2.0);
 
-        for (rigid_body, shape) in world.query::<(&RigidBody, &Shape)>() {
-            let transform = &rigid_body.transform;
+        // How far we are between the last two simulation steps
+        let alpha = match world.get_resource::<Time>() {
+            Some(time) => time.alpha(),
+            None => 1.0,
+        };
+
+        for (entity, shape) in world.query::<(Entity, &Shape)>() {
+            let transform = match render_transform(world, entity, alpha) {
+                Some(transform) => transform,
+                None => continue,
+            };
             // Move the sheet
             // Magical math to get zoom with focal point in center of screen
             canvas.translate(
@@ -63,3 +75,170 @@ impl System for SceneRenderer {
         }
     }
 }
+
+// Returns where to draw the given entity. Rigid bodies are interpolated between
+// the last two simulation steps. Entities in a hierarchy are drawn from the
+// global transform computed by the transform propagation system, relative to
+// wherever their root is drawn, so that they stay attached to it. Returns None
+// for entities that aren't placed anywhere in the world, including entities
+// that are (indirectly) their own parent.
+fn render_transform(world: &World, entity: Entity, alpha: f32) -> Option<Transform> {
+    if let Some(global) = world.get::<GlobalTransform>(entity) {
+        let root = match world.get::<RigidBody>(global.root) {
+            Some(rigid_body) => rigid_body.transform,
+            None => return Some(global.transform),
+        };
+        let relative = root.inverse().mul_transform(&global.transform);
+        return Some(interpolated_transform(world, global.root, alpha)?.mul_transform(&relative));
+    }
+
+    // Children are only placed once their transform has been propagated
+    if world.has::<Parent>(entity) {
+        return None;
+    }
+    interpolated_transform(world, entity, alpha)
+}
+
+// Returns the transform of an entity outside of any hierarchy, interpolated if
+// it's a rigid body.
+fn interpolated_transform(world: &World, entity: Entity, alpha: f32) -> Option<Transform> {
+    match (
+        world.get::<RigidBody>(entity),
+        world.get::<PreviousTransform>(entity),
+    ) {
+        (Some(rigid_body), Some(previous_transform)) => Some(
+            previous_transform
+                .0
+                .interpolate(&rigid_body.transform, alpha),
+        ),
+        (Some(rigid_body), None) => Some(rigid_body.transform),
+        (None, _) => Some(world.get::<LocalTransform>(entity)?.0),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::components::point::Point;
+    use crate::components::rigid_body::PhysicsMode;
+    use crate::components::shape::ColorRGBA;
+    use crate::resources::canvas::{DrawCommand, RecordingCanvas};
+    use crate::systems::transform_propagation::TransformPropagationSystem;
+    use rapier2d::na::Vector2;
+
+    fn shape() -> Shape {
+        Shape {
+            vertices: vec![Point { x: 0.0, y: 0.0 }, Point { x: 1.0, y: 1.0 }],
+            color: ColorRGBA {
+                r: 255,
+                g: 255,
+                b: 255,
+                a: 1.0,
+            },
+            is_sensor: false,
+        }
+    }
+
+    fn create_world() -> World {
+        let mut world = World::new();
+        world.create_resource(Canvas::new(RecordingCanvas::new(800.0, 600.0)));
+        world.create_resource(Viewport {
+            position: Vector2::new(0.0, 0.0),
+            target_position: Vector2::new(0.0, 0.0),
+            zoom: 1.0,
+            target_zoom: 1.0,
+        });
+        world
+    }
+
+    fn render(world: &mut World) -> Vec<DrawCommand> {
+        TransformPropagationSystem::new().update(world);
+        SceneRenderer::new().update(world);
+
+        let canvas = world.get_resource::<Canvas>().unwrap();
+        canvas
+            .backend::<RecordingCanvas>()
+            .unwrap()
+            .commands
+            .clone()
+    }
+
+    #[test]
+    fn test_parent_cycles_are_not_drawn() {
+        let mut world = create_world();
+
+        let own_parent = world
+            .create_entity()
+            .with_component(shape())
+            .with_component(LocalTransform(Transform::identity()))
+            .entity();
+        world.insert_component(own_parent, Parent(own_parent));
+
+        let first = world.create_entity().with_component(shape()).entity();
+        let second = world
+            .create_entity()
+            .with_component(shape())
+            .with_component(Parent(first))
+            .entity();
+        world.insert_component(first, Parent(second));
+
+        let root = world
+            .create_entity()
+            .with_component(shape())
+            .with_component(LocalTransform(Transform::identity()))
+            .entity();
+        world
+            .create_entity()
+            .with_component(shape())
+            .with_component(Parent(root))
+            .with_component(LocalTransform(Transform::identity()));
+
+        let paths = render(&mut world)
+            .iter()
+            .filter(|command| matches!(command, DrawCommand::Path { .. }))
+            .count();
+        assert_eq!(paths, 2);
+    }
+
+    #[test]
+    fn test_children_are_interpolated_with_their_root() {
+        let mut world = create_world();
+        let mut time = Time::new(0.125);
+        time.advance(0.0625);
+        world.create_resource(time);
+
+        let ship = world
+            .create_entity()
+            .with_component(shape())
+            .with_component(RigidBody {
+                transform: Transform {
+                    position: Vector2::new(100.0, 0.0),
+                    rotation: 0.0,
+                },
+                mass: 1.0,
+                linear_velocity: Vector2::new(0.0, 0.0),
+                angular_velocity: 0.0,
+                physics_mode: PhysicsMode::Dynamic,
+            })
+            .with_component(PreviousTransform(Transform::identity()))
+            .entity();
+        world
+            .create_entity()
+            .with_component(shape())
+            .with_component(Parent(ship))
+            .with_component(LocalTransform(Transform {
+                position: Vector2::new(10.0, 0.0),
+                rotation: 0.0,
+            }));
+
+        let mut translations: Vec<(f64, f64)> = render(&mut world)
+            .iter()
+            .filter_map(|command| match command {
+                DrawCommand::Translate { x, y } => Some((*x, *y)),
+                _ => None,
+            })
+            .collect();
+        translations.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(translations, vec![(450.0, 300.0), (460.0, 300.0)]);
+    }
+}
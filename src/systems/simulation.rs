@@ -1,16 +1,21 @@
 use std::collections::HashMap;
-use std::collections::HashSet;
 
 use crate::components::gravity::GravitySource;
 use crate::components::program::Program;
-use crate::components::rigid_body::{PhysicsMode, RigidBody, Transform};
+use crate::components::rigid_body::{PhysicsMode, PreviousTransform, RigidBody, Transform};
 use crate::components::shape::Shape;
 use crate::components::thrusters::Thrusters;
+use crate::entities::Entity;
+use crate::events::Events;
 use crate::interpreter::object::Command;
+use crate::query::Added;
+use crate::resources::time::Time;
 use crate::systems::System;
 use crate::world::World;
 use rapier2d::crossbeam;
-use rapier2d::geometry::{BroadPhase, ColliderSet, NarrowPhase};
+use rapier2d::geometry::{
+    BroadPhase, ColliderHandle, ColliderSet, ContactEvent, IntersectionEvent, NarrowPhase,
+};
 use rapier2d::pipeline::PhysicsPipeline;
 use rapier2d::{dynamics::BodyStatus, na::Vector2};
 use rapier2d::{
@@ -18,8 +23,17 @@ use rapier2d::{
     geometry::ColliderBuilder,
     pipeline::ChannelEventCollector,
 };
+
+/// Sent when two bodies start touching, or when a body enters a sensor.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CollisionStarted {
+    pub a: Entity,
+    pub b: Entity,
+}
+
 pub struct SimulationSystem {
-    body_handles: HashMap<usize, RigidBodyHandle>,
+    body_handles: HashMap<Entity, RigidBodyHandle>,
+    collider_entities: HashMap<ColliderHandle, Entity>,
     physics_pipeline: PhysicsPipeline,
     gravity: Vector2<f32>,
     integration_parameters: IntegrationParameters,
@@ -34,6 +48,7 @@ impl SimulationSystem {
     pub fn new() -> SimulationSystem {
         SimulationSystem {
             body_handles: HashMap::new(),
+            collider_entities: HashMap::new(),
             physics_pipeline: PhysicsPipeline::new(),
             gravity: Vector2::new(0.0, 0.0),
             integration_parameters: IntegrationParameters::default(),
@@ -45,7 +60,7 @@ impl SimulationSystem {
         }
     }
 
-    fn insert_body(&mut self, rigid_body: &RigidBody, shape: &Shape) {
+    fn insert_body(&mut self, entity: Entity, rigid_body: &RigidBody, shape: &Shape) {
         let body_status = match rigid_body.physics_mode {
             PhysicsMode::Dynamic => BodyStatus::Dynamic,
             PhysicsMode::Static => BodyStatus::Static,
@@ -62,9 +77,9 @@ impl SimulationSystem {
             .mass(rigid_body.mass)
             .build();
 
-        // Add loose mapping between rigid_body components and physics bodies
+        // Add mapping between entities and physics bodies
         let entity_handle = self.bodies.insert(entity_rb);
-        self.body_handles.insert(rigid_body.id, entity_handle);
+        self.body_handles.insert(entity, entity_handle);
 
         let mut points = Vec::new();
         for point in &shape.vertices {
@@ -76,51 +91,66 @@ impl SimulationSystem {
             .sensor(shape.is_sensor)
             .build();
 
-        self.colliders
-            .insert(entity_collider, entity_handle, &mut self.bodies);
+        let collider_handle =
+            self.colliders
+                .insert(entity_collider, entity_handle, &mut self.bodies);
+        self.collider_entities.insert(collider_handle, entity);
     }
 
-    fn remove_body(&mut self, id: &usize) {
-        let handle = self.body_handles.get(id).unwrap();
-        self.bodies
-            .remove(*handle, &mut self.colliders, &mut self.joints);
-        self.body_handles.remove(id);
+    fn remove_body(&mut self, entity: &Entity) {
+        if let Some(handle) = self.body_handles.remove(entity) {
+            self.collider_entities.retain(|_, other| other != entity);
+            self.bodies
+                .remove(handle, &mut self.colliders, &mut self.joints);
+        }
     }
 }
 
 impl System for SimulationSystem {
     fn update(&mut self, world: &mut World) {
-        let mut ids = HashSet::<usize>::new();
-
-        for (rigid_body, shape) in world.query::<(&RigidBody, &Shape)>() {
-            // Store the id of the rigid body in a set that we can use later to
-            // check if any entities have been removed.
-            ids.insert(rigid_body.id);
-
-            // Check if we have a rigid body handle for the given id. If it
-            // doesn't exists we insert it into Rapier.
-            if !self.body_handles.contains_key(&rigid_body.id) {
-                self.insert_body(rigid_body, shape);
-            }
+        // Remove the physics bodies for any rigid bodies that have been removed
+        // since the last update. This needs to happen before inserting new
+        // bodies, in case a rigid body was replaced.
+        for entity in world.removed_components::<RigidBody>() {
+            self.remove_body(&entity);
         }
 
-        // Compare the ids stored in the body handles cache with the current
-        // list of ids. If any of them are only present in our cache, it means
-        // that the entity have been removed.
-        let removed_ids: Vec<usize> = self
-            .body_handles
-            .keys()
-            .filter(|id| !ids.contains(id))
-            .map(|id| (*id).clone())
+        // Insert any rigid bodies that have been added since the last update
+        // into Rapier. A body needs a shape as well, which can be added later
+        // or replaced, in which case the existing body is replaced.
+        let mut added: Vec<Entity> = world
+            .query::<(Entity, &RigidBody, &Shape, Added<RigidBody>)>()
+            .into_iter()
+            .map(|(entity, _, _, _)| entity)
             .collect();
+        for (entity, _, _, _) in world.query::<(Entity, &RigidBody, &Shape, Added<Shape>)>() {
+            if !added.contains(&entity) {
+                added.push(entity);
+            }
+        }
+        for entity in added {
+            let rigid_body = world.get::<RigidBody>(entity).unwrap();
+            let shape = world.get::<Shape>(entity).unwrap();
+            self.remove_body(&entity);
+            self.insert_body(entity, rigid_body, shape);
+            world
+                .commands()
+                .insert_component(entity, PreviousTransform(rigid_body.transform));
+        }
 
-        for id in removed_ids {
-            self.remove_body(&id);
+        // Step the physics simulation by the same amount as the fixed time step
+        if let Some(time) = world.get_resource::<Time>() {
+            self.integration_parameters.dt = time.step;
         }
 
-        for (program, rigid_body, thrusters) in world.query::<(&Program, &RigidBody, &Thrusters)>()
+        for (entity, program, rigid_body, thrusters) in
+            world.query::<(Entity, &Program, &RigidBody, &Thrusters)>()
         {
-            let handle = self.body_handles.get(&rigid_body.id).unwrap();
+            // Entities without a shape don't have a physics body
+            let handle = match self.body_handles.get(&entity) {
+                Some(handle) => handle,
+                None => continue,
+            };
             let body = self.bodies.get_mut(*handle).unwrap();
 
             if thrusters.get_throttle() > 0.0 {
@@ -150,7 +180,7 @@ impl System for SimulationSystem {
             let gravity_sources = world.query::<(&GravitySource, &RigidBody)>();
 
             // Find all the entities that are supposed to be affected by gravity
-            for rigid_body in world.query::<&RigidBody>() {
+            for (entity, rigid_body) in world.query::<(Entity, &RigidBody)>() {
                 // It's like I say to the ladies;
                 // I won't process any static bodies
                 if let PhysicsMode::Static = rigid_body.physics_mode {
@@ -176,7 +206,10 @@ impl System for SimulationSystem {
                     }
                 }
 
-                let handle = self.body_handles.get(&rigid_body.id).unwrap();
+                let handle = match self.body_handles.get(&entity) {
+                    Some(handle) => handle,
+                    None => continue,
+                };
                 let body = self.bodies.get_mut(*handle).unwrap();
 
                 // Multiply gravity force with object mass to get consistent acceleration for bodies of different masses
@@ -200,10 +233,19 @@ impl System for SimulationSystem {
             &event_handler,
         );
 
-        let physics_bodies = self.bodies.iter();
-        let rigid_bodies = world.query_mut::<&mut RigidBody>().into_iter();
-
-        for ((_handle, physics_body), rigid_body) in physics_bodies.zip(rigid_bodies) {
+        // Copy the simulated state back to the components. The order of the
+        // query doesn't match the order of the physics bodies, so we need to
+        // look up each body by its entity.
+        for (entity, rigid_body, previous_transform) in
+            world.query_mut::<(Entity, &mut RigidBody, Option<&mut PreviousTransform>)>()
+        {
+            let physics_body = match self.body_handles.get(&entity) {
+                Some(handle) => self.bodies.get(*handle).unwrap(),
+                None => continue,
+            };
+            if let Some(previous_transform) = previous_transform {
+                previous_transform.0 = rigid_body.transform;
+            }
             rigid_body.transform = Transform {
                 position: Vector2::new(
                     physics_body.position().translation.x,
@@ -216,15 +258,36 @@ impl System for SimulationSystem {
             rigid_body.angular_velocity = physics_body.angvel();
         }
 
-        while let Ok(intersection_event) = intersection_recv.try_recv() {
-            let _parent = self
-                .colliders
-                .get(intersection_event.collider1)
-                .unwrap()
-                .parent();
+        // Let any other systems know about the bodies that started touching
+        // during this step. Contacts between solid bodies and intersections
+        // with sensors are both reported as collisions.
+        let mut collisions = vec![];
+        while let Ok(event) = contact_recv.try_recv() {
+            if let ContactEvent::Started(first, second) = event {
+                collisions.push((first, second));
+            }
+        }
+        while let Ok(IntersectionEvent {
+            collider1,
+            collider2,
+            intersecting,
+        }) = intersection_recv.try_recv()
+        {
+            if intersecting {
+                collisions.push((collider1, collider2));
+            }
         }
 
-        while let Ok(_contact_event) = contact_recv.try_recv() {}
+        if let Some(mut events) = world.get_resource_mut::<Events<CollisionStarted>>() {
+            for (first, second) in collisions {
+                if let (Some(a), Some(b)) = (
+                    self.collider_entities.get(&first),
+                    self.collider_entities.get(&second),
+                ) {
+                    events.send(CollisionStarted { a: *a, b: *b });
+                }
+            }
+        }
     }
 }
 
@@ -241,15 +304,11 @@ mod test {
     fn test_update_inserts_new_bodies() {
         let mut system = SimulationSystem::new();
         let mut world = World::new();
+        let mut last_run = 0;
 
-        world.register_component::<RigidBody>();
-        world.register_component::<Shape>();
-        world.register_component::<Program>();
-        world.register_component::<GravitySource>();
-        world.register_component::<Thrusters>();
-        system.update(&mut world);
+        world.run_system(&mut system, &mut last_run);
 
-        world
+        let entity = world
             .create_entity()
             .with_component(Shape {
                 is_sensor: false,
@@ -267,7 +326,6 @@ mod test {
                 },
             })
             .with_component(RigidBody {
-                id: 0,
                 transform: Transform {
                     position: Vector2::new(0.0, 0.0),
                     rotation: 0.0,
@@ -286,11 +344,12 @@ mod test {
                     rotation: 0.0,
                     fuel_consumption_per_force: 0.001,
                 }],
-            ));
+            ))
+            .entity();
 
-        system.update(&mut world);
+        world.run_system(&mut system, &mut last_run);
 
-        assert!(system.body_handles.contains_key(&0));
+        assert!(system.body_handles.contains_key(&entity));
         assert_eq!(system.colliders.len(), 1);
         assert_eq!(system.bodies.len(), 1);
     }
@@ -299,14 +358,9 @@ mod test {
     fn test_update_removes_old_bodies() {
         let mut system = SimulationSystem::new();
         let mut world = World::new();
+        let mut last_run = 0;
 
-        world.register_component::<RigidBody>();
-        world.register_component::<Shape>();
-        world.register_component::<Program>();
-        world.register_component::<GravitySource>();
-        world.register_component::<Thrusters>();
-
-        world
+        let entity = world
             .create_entity()
             .with_component(Shape {
                 is_sensor: false,
@@ -324,7 +378,6 @@ mod test {
                 },
             })
             .with_component(RigidBody {
-                id: 0,
                 transform: Transform {
                     position: Vector2::new(0.0, 0.0),
                     rotation: 0.0,
@@ -343,16 +396,165 @@ mod test {
                     rotation: 0.0,
                     fuel_consumption_per_force: 0.001,
                 }],
-            ));
+            ))
+            .entity();
 
-        system.update(&mut world);
+        world.run_system(&mut system, &mut last_run);
 
-        world.remove_entity(0);
+        world.remove_entity(entity);
 
-        system.update(&mut world);
+        world.run_system(&mut system, &mut last_run);
 
-        assert!(system.body_handles.contains_key(&0) == false);
+        assert!(system.body_handles.contains_key(&entity) == false);
         assert_eq!(system.colliders.len(), 0);
         assert_eq!(system.bodies.len(), 0);
     }
+
+    #[test]
+    fn test_update_only_inserts_bodies_once() {
+        let mut system = SimulationSystem::new();
+        let mut world = World::new();
+        let mut last_run = 0;
+
+        let entity = world
+            .create_entity()
+            .with_component(Shape {
+                is_sensor: false,
+                vertices: vec![
+                    Point { x: -1.0, y: -1.0 },
+                    Point { x: 1.0, y: -1.0 },
+                    Point { x: 1.0, y: 1.0 },
+                    Point { x: -1.0, y: 1.0 },
+                ],
+                color: ColorRGBA {
+                    r: 255,
+                    g: 255,
+                    b: 255,
+                    a: 1.0,
+                },
+            })
+            .with_component(RigidBody {
+                transform: Transform {
+                    position: Vector2::new(0.0, 0.0),
+                    rotation: 0.0,
+                },
+                mass: 1.0,
+                linear_velocity: Vector2::new(0.0, 0.0),
+                angular_velocity: 0.0,
+                physics_mode: PhysicsMode::Dynamic,
+            })
+            .entity();
+
+        world.run_system(&mut system, &mut last_run);
+        world.run_system(&mut system, &mut last_run);
+
+        assert_eq!(system.bodies.len(), 1);
+
+        world.remove_component::<RigidBody>(entity);
+        world.run_system(&mut system, &mut last_run);
+
+        assert!(!system.body_handles.contains_key(&entity));
+        assert_eq!(system.bodies.len(), 0);
+    }
+
+    #[test]
+    fn test_update_inserts_bodies_when_shape_is_added() {
+        let mut system = SimulationSystem::new();
+        let mut world = World::new();
+        let mut last_run = 0;
+
+        let entity = create_box(&mut world, 0.0, 0.0);
+        world.remove_component::<Shape>(entity);
+
+        world.run_system(&mut system, &mut last_run);
+        assert!(!system.body_handles.contains_key(&entity));
+
+        world.insert_component(entity, create_square());
+        world.run_system(&mut system, &mut last_run);
+        assert!(system.body_handles.contains_key(&entity));
+        assert_eq!(system.bodies.len(), 1);
+
+        // Adding the shape again replaces the body
+        world.remove_component::<Shape>(entity);
+        world.insert_component(entity, create_square());
+        world.run_system(&mut system, &mut last_run);
+        assert_eq!(system.bodies.len(), 1);
+        assert_eq!(system.colliders.len(), 1);
+    }
+
+    #[test]
+    fn test_update_skips_ships_without_shape() {
+        let mut system = SimulationSystem::new();
+        let mut world = World::new();
+        let mut last_run = 0;
+
+        let entity = create_box(&mut world, 0.0, 0.0);
+        world.remove_component::<Shape>(entity);
+        world.insert_component(entity, Program::new());
+        world.insert_component(entity, Thrusters::new(10.0, 10.0, vec![]));
+
+        world.run_system(&mut system, &mut last_run);
+        assert!(!system.body_handles.contains_key(&entity));
+    }
+
+    fn create_square() -> Shape {
+        Shape {
+            is_sensor: false,
+            vertices: vec![
+                Point { x: -1.0, y: -1.0 },
+                Point { x: 1.0, y: -1.0 },
+                Point { x: 1.0, y: 1.0 },
+                Point { x: -1.0, y: 1.0 },
+            ],
+            color: ColorRGBA {
+                r: 255,
+                g: 255,
+                b: 255,
+                a: 1.0,
+            },
+        }
+    }
+
+    fn create_box(world: &mut World, x: f32, velocity: f32) -> Entity {
+        world
+            .create_entity()
+            .with_component(create_square())
+            .with_component(RigidBody {
+                transform: Transform {
+                    position: Vector2::new(x, 0.0),
+                    rotation: 0.0,
+                },
+                mass: 1.0,
+                linear_velocity: Vector2::new(velocity, 0.0),
+                angular_velocity: 0.0,
+                physics_mode: PhysicsMode::Dynamic,
+            })
+            .entity()
+    }
+
+    #[test]
+    fn test_update_sends_collision_events() {
+        let mut system = SimulationSystem::new();
+        let mut world = World::new();
+        let mut last_run = 0;
+        world.add_event::<CollisionStarted>();
+        let mut reader = world
+            .get_resource::<Events<CollisionStarted>>()
+            .unwrap()
+            .reader();
+
+        let first = create_box(&mut world, -3.0, 10.0);
+        let second = create_box(&mut world, 3.0, -10.0);
+
+        let mut collisions = vec![];
+        for _ in 0..60 {
+            world.run_system(&mut system, &mut last_run);
+            let events = world.get_resource::<Events<CollisionStarted>>().unwrap();
+            collisions.extend(reader.read(&events).into_iter().copied());
+        }
+
+        assert_eq!(collisions.len(), 1);
+        let CollisionStarted { a, b } = collisions[0];
+        assert!((a, b) == (first, second) || (a, b) == (second, first));
+    }
 }
@@ -0,0 +1,251 @@
+use hashbrown::{HashMap, HashSet};
+
+use crate::components::hierarchy::{Children, GlobalTransform, LocalTransform, Parent};
+use crate::components::rigid_body::{RigidBody, Transform};
+use crate::entities::Entity;
+use crate::query::Without;
+use crate::systems::System;
+use crate::world::World;
+
+/// Computes the `GlobalTransform` of every entity in a hierarchy, starting at
+/// the root and working down through the children. Also keeps `Children` in
+/// sync with the `Parent` components, and removes any children whose parent
+/// has been removed. Should run after the simulation, so that children follow
+/// the latest position of their parent. The scene renderer draws hierarchies
+/// from the global transforms.
+pub struct TransformPropagationSystem {}
+
+impl TransformPropagationSystem {
+    pub fn new() -> Self {
+        TransformPropagationSystem {}
+    }
+}
+
+impl System for TransformPropagationSystem {
+    fn update(&mut self, world: &mut World) {
+        let mut children: HashMap<Entity, Vec<Entity>> = HashMap::new();
+        for (entity, parent) in world.query::<(Entity, &Parent)>() {
+            children.entry(parent.0).or_default().push(entity);
+        }
+
+        // Children can't exist without their parent, so remove them (and
+        // their own children) along with it. They are removed once the system
+        // is done, and are left out of everything below.
+        let orphans: Vec<Entity> = children
+            .keys()
+            .filter(|parent| !world.is_alive(**parent))
+            .copied()
+            .collect();
+        for orphan in orphans {
+            let mut stack = vec![orphan];
+            while let Some(entity) = stack.pop() {
+                if let Some(entity_children) = children.remove(&entity) {
+                    for child in entity_children {
+                        world.commands().despawn(child);
+                        stack.push(child);
+                    }
+                }
+            }
+        }
+
+        // Update the children of every parent, and remove the component from
+        // entities that no longer have any.
+        let outdated: Vec<Entity> = world
+            .query::<(Entity, &Children)>()
+            .into_iter()
+            .filter(|(entity, _)| !children.contains_key(entity))
+            .map(|(entity, _)| entity)
+            .collect();
+        for entity in outdated {
+            world.remove_component::<Children>(entity);
+        }
+        for (parent, entity_children) in &children {
+            if world.get::<Children>(*parent) != Some(&Children(entity_children.clone())) {
+                world.insert_component(*parent, Children(entity_children.clone()));
+            }
+        }
+
+        // Walk down from each root. Rigid bodies are positioned by the
+        // simulation, any other root by its local transform.
+        let roots: Vec<Entity> = world
+            .query::<(Entity, Without<Parent>)>()
+            .into_iter()
+            .map(|(entity, _)| entity)
+            .filter(|entity| children.contains_key(entity))
+            .collect();
+        let mut visited = HashSet::new();
+        for root in roots {
+            let transform = match world.get::<RigidBody>(root) {
+                Some(rigid_body) => rigid_body.transform,
+                None => local_transform(world, root),
+            };
+
+            let mut stack = vec![(root, transform)];
+            while let Some((entity, transform)) = stack.pop() {
+                // Guard against entities that are (indirectly) their own
+                // parent, which would otherwise loop forever.
+                if !visited.insert(entity) {
+                    continue;
+                }
+
+                set_global_transform(world, entity, transform, root);
+                for child in children.get(&entity).into_iter().flatten() {
+                    let local = local_transform(world, *child);
+                    stack.push((*child, transform.mul_transform(&local)));
+                }
+            }
+        }
+
+        // Entities that have left their hierarchy (or are part of a cycle)
+        // aren't placed anywhere by it anymore.
+        let detached: Vec<Entity> = world
+            .query::<(Entity, &GlobalTransform)>()
+            .into_iter()
+            .filter(|(entity, _)| !visited.contains(entity))
+            .map(|(entity, _)| entity)
+            .collect();
+        for entity in detached {
+            world.remove_component::<GlobalTransform>(entity);
+        }
+    }
+}
+
+fn local_transform(world: &World, entity: Entity) -> Transform {
+    match world.get::<LocalTransform>(entity) {
+        Some(local) => local.0,
+        None => Transform::identity(),
+    }
+}
+
+fn set_global_transform(world: &mut World, entity: Entity, transform: Transform, root: Entity) {
+    let global = GlobalTransform { transform, root };
+    match world.get_mut::<GlobalTransform>(entity) {
+        Some(current) => *current = global,
+        None => world.insert_component(entity, global),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::components::rigid_body::PhysicsMode;
+    use rapier2d::na::Vector2;
+    use std::f32::consts::PI;
+
+    fn transform(x: f32, y: f32, rotation: f32) -> Transform {
+        Transform {
+            position: Vector2::new(x, y),
+            rotation,
+        }
+    }
+
+    fn create_ship(world: &mut World) -> Entity {
+        world
+            .create_entity()
+            .with_component(RigidBody {
+                transform: transform(100.0, 0.0, PI / 2.0),
+                mass: 1.0,
+                linear_velocity: Vector2::new(0.0, 0.0),
+                angular_velocity: 0.0,
+                physics_mode: PhysicsMode::Dynamic,
+            })
+            .entity()
+    }
+
+    fn assert_transform_eq(a: Transform, b: Transform) {
+        assert!(
+            (a.position - b.position).magnitude() < 0.0001,
+            "{:?} != {:?}",
+            a,
+            b
+        );
+        assert!(
+            (a.rotation - b.rotation).abs() < 0.0001,
+            "{:?} != {:?}",
+            a,
+            b
+        );
+    }
+
+    #[test]
+    fn test_propagates_global_transforms() {
+        let mut world = World::new();
+        let mut system = TransformPropagationSystem::new();
+        let mut last_run = 0;
+
+        let ship = create_ship(&mut world);
+        let pod = world
+            .create_entity()
+            .with_component(Parent(ship))
+            .with_component(LocalTransform(transform(10.0, 0.0, 0.0)))
+            .entity();
+        let antenna = world
+            .create_entity()
+            .with_component(Parent(pod))
+            .with_component(LocalTransform(transform(0.0, 5.0, 0.5)))
+            .entity();
+
+        world.run_system(&mut system, &mut last_run);
+
+        assert_eq!(world.get::<Children>(ship), Some(&Children(vec![pod])));
+        assert_eq!(world.get::<Children>(pod), Some(&Children(vec![antenna])));
+        assert_transform_eq(
+            world.get::<GlobalTransform>(ship).unwrap().transform,
+            transform(100.0, 0.0, PI / 2.0),
+        );
+        assert_transform_eq(
+            world.get::<GlobalTransform>(pod).unwrap().transform,
+            transform(100.0, 10.0, PI / 2.0),
+        );
+        assert_transform_eq(
+            world.get::<GlobalTransform>(antenna).unwrap().transform,
+            transform(95.0, 10.0, PI / 2.0 + 0.5),
+        );
+        assert_eq!(world.get::<GlobalTransform>(antenna).unwrap().root, ship);
+
+        // Children follow their parent when it moves
+        world.get_mut::<RigidBody>(ship).unwrap().transform = transform(0.0, 0.0, 0.0);
+        world.run_system(&mut system, &mut last_run);
+        assert_transform_eq(
+            world.get::<GlobalTransform>(antenna).unwrap().transform,
+            transform(10.0, 5.0, 0.5),
+        );
+    }
+
+    #[test]
+    fn test_updates_children() {
+        let mut world = World::new();
+        let mut system = TransformPropagationSystem::new();
+        let mut last_run = 0;
+
+        let ship = create_ship(&mut world);
+        let pod = world.create_entity().with_component(Parent(ship)).entity();
+
+        world.run_system(&mut system, &mut last_run);
+        assert_eq!(world.get::<Children>(ship), Some(&Children(vec![pod])));
+
+        world.remove_component::<Parent>(pod);
+        world.run_system(&mut system, &mut last_run);
+        assert!(!world.has::<Children>(ship));
+        assert!(!world.has::<GlobalTransform>(ship));
+        assert!(!world.has::<GlobalTransform>(pod));
+    }
+
+    #[test]
+    fn test_removes_orphans() {
+        let mut world = World::new();
+        let mut system = TransformPropagationSystem::new();
+        let mut last_run = 0;
+
+        let ship = create_ship(&mut world);
+        let pod = world.create_entity().with_component(Parent(ship)).entity();
+        let antenna = world.create_entity().with_component(Parent(pod)).entity();
+
+        world.run_system(&mut system, &mut last_run);
+        world.remove_entity(ship);
+        world.run_system(&mut system, &mut last_run);
+
+        assert!(!world.is_alive(pod));
+        assert!(!world.is_alive(antenna));
+    }
+}
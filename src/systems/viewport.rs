@@ -14,6 +14,7 @@ impl ViewportSystem {
 impl System for ViewportSystem {
     fn update(&mut self, world: &mut World) {
         let input = world.get_resource::<Input>().unwrap();
+        let mut viewport = world.get_resource_mut::<Viewport>().unwrap();
         let mut x = 0.0;
         let mut y = 0.0;
         let mut zoom = 0.0;
@@ -29,10 +30,12 @@ impl System for ViewportSystem {
             }
         }
 
-        let viewport = world.get_resource_mut::<Viewport>().unwrap();
         let movement_step = 15.0;
         let zoom_step = 0.04;
 
         viewport.move_target(x * movement_step, y * movement_step, zoom * zoom_step);
+
+        // Animate toward the target on every frame, even if the game is paused
+        viewport.move_toward_target();
     }
 }
@@ -1,16 +1,31 @@
-use crate::entities::Entities;
+use crate::commands::Commands;
+use crate::entities::{Entities, Entity};
+use crate::events::Events;
 use crate::query::{Query, QueryMut};
-use hashbrown::HashMap;
-use instant::Instant;
-use std::any::{Any, TypeId};
+use crate::registry::Registry;
+use crate::systems::System;
+use hashbrown::{HashMap, HashSet};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::any::{type_name, Any, TypeId};
+use std::cell::{Ref, RefCell, RefMut};
+use std::ops::{Deref, DerefMut};
 
 // Our world holds all our entities and components. The actual components are
 // stored inside the Entites struct so we can reuse the implementation between
 // this struct and our Query implementation.
 pub struct World {
-    resources: HashMap<TypeId, Box<dyn Any>>,
+    // Each resource is stored in a RefCell, so that systems can borrow
+    // several resources mutably at the same time. The borrow rules are then
+    // checked at runtime instead.
+    resources: HashMap<TypeId, RefCell<Box<dyn Any>>>,
     entities: Entities,
-    pub start_timestamp: Instant, // TODO: Remove in favor of a Resource
+    commands: RefCell<Commands>,
+    // Swaps the buffers of every event type added with `add_event`.
+    event_updaters: Vec<fn(&World)>,
+    // The types that are included in snapshots.
+    registry: Registry,
 }
 
 impl World {
@@ -18,64 +33,254 @@ impl World {
         World {
             resources: HashMap::new(),
             entities: Entities::new(),
-            start_timestamp: Instant::now(),
+            commands: RefCell::new(Commands::new()),
+            event_updaters: vec![],
+            registry: Registry::new(),
         }
     }
 
-    /// Returns a resource of the given type.
-    pub fn get_resource<T: 'static>(&self) -> Option<&T> {
+    /// Returns a resource of the given type. The resource is borrowed until
+    /// the returned `Res` is dropped, and will panic if the resource is
+    /// already borrowed mutably.
+    pub fn get_resource<T: 'static>(&self) -> Option<Res<'_, T>> {
         let id = TypeId::of::<T>();
-        if let Some(data) = self.resources.get(&id) {
-            data.downcast_ref()
-        } else {
-            None
-        }
+        let cell = self.resources.get(&id)?;
+        let data = cell.try_borrow().unwrap_or_else(|_| {
+            panic!(
+                "resource `{}` is already borrowed mutably",
+                type_name::<T>()
+            )
+        });
+        Some(Res(Ref::map(data, |data| data.downcast_ref().unwrap())))
     }
 
-    /// Returns a mutable reference to the resource of the given type.
-    pub fn get_resource_mut<T: 'static>(&mut self) -> Option<&mut T> {
+    /// Returns a mutable reference to the resource of the given type. Since
+    /// this only needs a shared reference to the world, several resources can
+    /// be borrowed mutably at once. Will panic if the resource is already
+    /// borrowed.
+    pub fn get_resource_mut<T: 'static>(&self) -> Option<ResMut<'_, T>> {
         let id = TypeId::of::<T>();
-        if let Some(data) = self.resources.get_mut(&id) {
-            data.downcast_mut()
-        } else {
-            None
-        }
+        let cell = self.resources.get(&id)?;
+        let data = cell
+            .try_borrow_mut()
+            .unwrap_or_else(|_| panic!("resource `{}` is already borrowed", type_name::<T>()));
+        Some(ResMut(RefMut::map(data, |data| {
+            data.downcast_mut().unwrap()
+        })))
     }
 
     /// Inserts the given resource. This will override any existing resource
     /// with the same type.
     pub fn create_resource<T: 'static>(&mut self, resource: T) {
         let id = TypeId::of::<T>();
-        self.resources.insert(id, Box::new(resource));
+        self.resources.insert(id, RefCell::new(Box::new(resource)));
     }
 
-    /// Register a component of a given type. Must be called before using the
-    /// `create_entity` method, or querying for that type.
-    pub fn register_component<T: 'static>(&mut self) {
-        self.entities.register_component::<T>();
+    /// Adds an `Events<T>` resource for the given event type, which will be
+    /// updated by `update_events`. Does nothing if it has already been added.
+    pub fn add_event<T: 'static>(&mut self) {
+        if self.get_resource::<Events<T>>().is_none() {
+            self.create_resource(Events::<T>::new());
+            self.event_updaters.push(|world| {
+                world.get_resource_mut::<Events<T>>().unwrap().update();
+            });
+        }
     }
 
-    /// Create a new entity. Returns an instance of self that can be used to add
-    /// components for that entity.
-    pub fn create_entity(&mut self) -> &mut Self {
-        self.entities.create_entity();
-        self
+    /// Sends an event to every reader. Will panic if the event type hasn't
+    /// been added using `add_event`.
+    pub fn send_event<T: 'static>(&self, event: T) {
+        self.get_resource_mut::<Events<T>>()
+            .unwrap_or_else(|| panic!("event `{}` has not been added", type_name::<T>()))
+            .send(event);
     }
 
-    /// Inserts the given component at the index of the current entity.
-    pub fn with_component<T: 'static>(&mut self, component: T) -> &mut Self {
-        self.entities.insert_component(component);
-        self
+    /// Drops the oldest events of every type. Should be called once per
+    /// frame, see `Events` for more details.
+    pub fn update_events(&self) {
+        for update in &self.event_updaters {
+            update(self);
+        }
+    }
+
+    /// Create a new entity. Returns an `EntityBuilder` that can be used to add
+    /// components for that entity, and to get the handle for it.
+    pub fn create_entity(&mut self) -> EntityBuilder<'_> {
+        let entity = self.entities.create_entity();
+        EntityBuilder {
+            world: self,
+            entity,
+        }
+    }
+
+    /// Returns true if the entity exists, and hasn't been removed.
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.entities.is_alive(entity)
+    }
+
+    /// Returns the component of the given type for a single entity, or None if
+    /// the entity doesn't have one.
+    pub fn get<T: 'static>(&self, entity: Entity) -> Option<&T> {
+        self.entities.get_component::<T>(entity)
+    }
+
+    /// Returns a mutable reference to the component of the given type for a
+    /// single entity.
+    pub fn get_mut<T: 'static>(&mut self, entity: Entity) -> Option<&mut T> {
+        self.entities.get_component_mut::<T>(entity)
+    }
+
+    /// Returns true if the entity has a component of the given type.
+    pub fn has<T: 'static>(&self, entity: Entity) -> bool {
+        self.entities.has_component::<T>(entity)
+    }
+
+    /// Inserts a component for an existing entity, replacing any component of
+    /// the same type it already has.
+    pub fn insert_component<T: 'static>(&mut self, entity: Entity, component: T) {
+        self.entities.insert_component(entity, component);
     }
 
-    /// Removes all components for a given entity identifier.
-    pub fn remove_entity(&mut self, entity_id: usize) {
-        self.entities.remove_entity(entity_id);
+    /// Removes all components for a given entity. Any handles to the entity
+    /// will be stale afterwards.
+    pub fn remove_entity(&mut self, entity: Entity) {
+        self.entities.remove_entity(entity);
     }
 
     /// Removes the component of the given type for a specific entity.
-    pub fn remove_component<T: 'static>(&mut self, entity_id: usize) {
-        self.entities.remove_component::<T>(entity_id);
+    pub fn remove_component<T: 'static>(&mut self, entity: Entity) {
+        self.entities.remove_component::<T>(entity);
+    }
+
+    /// Returns the command buffer, which can be used to record changes while
+    /// iterating over a query. See `Commands` for more details.
+    pub fn commands(&self) -> RefMut<'_, Commands> {
+        self.commands.borrow_mut()
+    }
+
+    /// Applies all the commands recorded since the last time this was called.
+    pub fn apply_commands(&mut self) {
+        let mut commands = self.commands.replace(Commands::new());
+        commands.apply(self);
+    }
+
+    /// Returns the entities that have had a component of the given type
+    /// removed since the current system last ran. Removed entities are
+    /// included for every component type they had.
+    pub fn removed_components<T: 'static>(&self) -> Vec<Entity> {
+        self.entities.removed_components::<T>()
+    }
+
+    /// Forgets about components removed up until the given tick. Should be
+    /// called once every system has had a chance to see them.
+    pub fn clear_removed_components(&mut self, tick: u64) {
+        self.entities.clear_removed_components(tick);
+    }
+
+    /// Runs a system with change detection relative to the tick it last ran
+    /// at, and updates the tick afterwards. Any commands recorded by the
+    /// system are applied once it's done.
+    pub fn run_system(&mut self, system: &mut dyn System, last_run: &mut u64) {
+        self.entities.last_change_tick = *last_run;
+        *last_run = self.entities.change_tick;
+        system.update(self);
+        self.apply_commands();
+        // Move on to the next tick, so that anything that changes after this
+        // point will be seen by the system the next time it runs.
+        self.entities.change_tick += 1;
+    }
+
+    /// Includes components of the given type in snapshots, using the name as
+    /// the key.
+    pub fn register_component<T>(&mut self, name: &'static str)
+    where
+        T: Serialize + DeserializeOwned + 'static,
+    {
+        self.registry.register_component::<T>(name);
+    }
+
+    /// Includes the resource of the given type in snapshots, using the name as
+    /// the key.
+    pub fn register_resource<T>(&mut self, name: &'static str)
+    where
+        T: Serialize + DeserializeOwned + 'static,
+    {
+        self.registry.register_resource::<T>(name);
+    }
+
+    /// Serializes every entity, along with its registered components, and
+    /// every registered resource to JSON.
+    pub fn snapshot(&self) -> String {
+        let mut entities = self.query::<Entity>();
+        entities.sort_by_key(|entity| entity.index);
+
+        let snapshot = Snapshot {
+            entities: entities
+                .into_iter()
+                .map(|entity| EntitySnapshot {
+                    entity,
+                    components: self.registry.save_components(self, entity),
+                })
+                .collect(),
+            resources: self.registry.save_resources(self),
+        };
+        serde_json::to_string(&snapshot).expect("failed to serialize snapshot")
+    }
+
+    /// Replaces every entity in the world with the ones in the snapshot, and
+    /// overrides the resources it contains. The entities keep the handles they
+    /// had when the snapshot was taken. Components and resources that aren't
+    /// registered are left out, so they have to be computed by the systems
+    /// again. The world is left untouched if the snapshot is invalid.
+    pub fn restore(&mut self, snapshot: &str) -> Result<(), String> {
+        let snapshot: Snapshot =
+            serde_json::from_str(snapshot).map_err(|error| error.to_string())?;
+
+        // Restoring creates every slot up to the highest index, so an index
+        // from a broken snapshot could allocate an enormous number of them.
+        let max_index = snapshot.entities.len() + self.entities.slot_count();
+        let mut handles = HashSet::new();
+        let mut entities = vec![];
+        for entity_snapshot in snapshot.entities {
+            if entity_snapshot.entity.index >= max_index {
+                return Err(format!(
+                    "entity index {} is out of range",
+                    entity_snapshot.entity.index
+                ));
+            }
+            if !handles.insert(entity_snapshot.entity) {
+                return Err(format!("duplicate entity {:?}", entity_snapshot.entity));
+            }
+            let mut loaders = vec![];
+            for (name, value) in entity_snapshot.components {
+                loaders.push(self.registry.load_component(&name, value)?);
+            }
+            entities.push((entity_snapshot.entity, loaders));
+        }
+
+        let mut resources = vec![];
+        for (name, value) in snapshot.resources {
+            resources.push(self.registry.load_resource(&name, value)?);
+        }
+
+        // Removing the existing entities (rather than starting over) lets the
+        // systems know they're gone, and every restored component will be
+        // seen as added.
+        for entity in self.query::<Entity>() {
+            self.remove_entity(entity);
+        }
+        for (entity, loaders) in entities {
+            self.entities.create_entity_at(entity);
+            for loader in loaders {
+                loader(self, entity);
+            }
+        }
+        self.entities.rebuild_free_list();
+        for loader in resources {
+            loader(self);
+        }
+
+        Ok(())
     }
 
     /// Query the world for components based on the generic type. See the
@@ -92,13 +297,74 @@ impl World {
     }
 }
 
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    entities: Vec<EntitySnapshot>,
+    resources: Map<String, Value>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct EntitySnapshot {
+    entity: Entity,
+    components: Map<String, Value>,
+}
+
+/// A shared borrow of a resource, returned by `World::get_resource`.
+pub struct Res<'a, T>(Ref<'a, T>);
+
+impl<'a, T> Deref for Res<'a, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+/// A mutable borrow of a resource, returned by `World::get_resource_mut`.
+pub struct ResMut<'a, T>(RefMut<'a, T>);
+
+impl<'a, T> Deref for ResMut<'a, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<'a, T> DerefMut for ResMut<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+/// Returned when creating a new entity. Allows us to chain calls to
+/// `with_component` when setting up a scene, while still being able to get the
+/// handle of the entity we just created.
+pub struct EntityBuilder<'a> {
+    world: &'a mut World,
+    entity: Entity,
+}
+
+impl<'a> EntityBuilder<'a> {
+    /// Inserts the given component for the entity being built.
+    pub fn with_component<T: 'static>(&mut self, component: T) -> &mut Self {
+        self.world.entities.insert_component(self.entity, component);
+        self
+    }
+
+    /// Returns the handle of the entity being built.
+    pub fn entity(&self) -> Entity {
+        self.entity
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::events::EventReader;
+    use crate::query::{Added, Changed, With, Without};
 
-    #[derive(Debug, PartialEq, Clone)]
+    #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
     struct Size(f32);
-    #[derive(Debug, PartialEq, Clone)]
+    #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
     struct Location {
         x: f32,
         y: f32,
@@ -107,7 +373,6 @@ mod test {
     #[test]
     fn test_query() {
         let mut world = World::new();
-        world.register_component::<Location>();
         world
             .create_entity()
             .with_component(Location { x: 10.0, y: 10.0 });
@@ -120,8 +385,6 @@ mod test {
     #[test]
     fn test_query_tuple_two() {
         let mut world = World::new();
-        world.register_component::<Location>();
-        world.register_component::<Size>();
         world
             .create_entity()
             .with_component(Location { x: 10.0, y: 10.0 });
@@ -138,9 +401,6 @@ mod test {
     #[test]
     fn test_query_tuple_three() {
         let mut world = World::new();
-        world.register_component::<Location>();
-        world.register_component::<Size>();
-        world.register_component::<bool>();
 
         world
             .create_entity()
@@ -158,10 +418,22 @@ mod test {
         assert_eq!(*components.2, true);
     }
 
+    #[test]
+    fn test_query_unknown_component() {
+        let mut world = World::new();
+        world
+            .create_entity()
+            .with_component(Location { x: 10.0, y: 10.0 });
+
+        assert!(world.query::<&Size>().is_empty());
+        assert!(world.query::<(&Location, &Size)>().is_empty());
+        assert!(world.query_mut::<&mut Size>().is_empty());
+        assert!(world.query_mut::<(&mut Location, &Size)>().is_empty());
+    }
+
     #[test]
     fn test_query_mut() {
         let mut world = World::new();
-        world.register_component::<Location>();
         world
             .create_entity()
             .with_component(Location { x: 10.0, y: 10.0 });
@@ -174,8 +446,6 @@ mod test {
     #[test]
     fn test_query_mut_tuple_two() {
         let mut world = World::new();
-        world.register_component::<Location>();
-        world.register_component::<Size>();
         world
             .create_entity()
             .with_component(Location { x: 10.0, y: 10.0 });
@@ -192,9 +462,6 @@ mod test {
     #[test]
     fn test_query_mut_tuple_three() {
         let mut world = World::new();
-        world.register_component::<Location>();
-        world.register_component::<Size>();
-        world.register_component::<bool>();
 
         world
             .create_entity()
@@ -212,11 +479,294 @@ mod test {
         assert_eq!(*components.2, true);
     }
 
+    #[test]
+    fn test_query_entity() {
+        let mut world = World::new();
+        let first = world
+            .create_entity()
+            .with_component(Location { x: 10.0, y: 10.0 })
+            .entity();
+        let second = world
+            .create_entity()
+            .with_component(Size(40.0))
+            .with_component(Location { x: 20.0, y: 20.0 })
+            .entity();
+
+        let entities = world.query::<Entity>();
+        assert_eq!(entities, vec![first, second]);
+
+        let (entity, size) = world.query::<(Entity, &Size)>()[0];
+        assert_eq!(entity, second);
+        assert_eq!(*size, Size(40.0));
+    }
+
+    #[test]
+    fn test_query_mut_entity() {
+        let mut world = World::new();
+        world
+            .create_entity()
+            .with_component(Location { x: 10.0, y: 10.0 });
+        let second = world
+            .create_entity()
+            .with_component(Size(40.0))
+            .with_component(Location { x: 20.0, y: 20.0 })
+            .entity();
+
+        for (entity, location, _size) in world.query_mut::<(Entity, &mut Location, &Size)>() {
+            assert_eq!(entity, second);
+            location.x = 30.0;
+        }
+
+        let entities: Vec<Entity> = world
+            .query::<(Entity, &Location)>()
+            .into_iter()
+            .filter(|(_, location)| location.x == 30.0)
+            .map(|(entity, _)| entity)
+            .collect();
+        assert_eq!(entities, vec![second]);
+
+        world.remove_entity(entities[0]);
+        assert_eq!(world.query::<&Location>().len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "each component type can only be used once in the same query")]
+    fn test_query_mut_same_component_twice() {
+        let mut world = World::new();
+        world
+            .create_entity()
+            .with_component(Location { x: 10.0, y: 10.0 });
+
+        world.query_mut::<(&mut Location, &mut Location)>();
+    }
+
+    #[test]
+    fn test_query_with_and_without() {
+        let mut world = World::new();
+        world
+            .create_entity()
+            .with_component(Location { x: 10.0, y: 10.0 });
+        world
+            .create_entity()
+            .with_component(Size(40.0))
+            .with_component(Location { x: 20.0, y: 20.0 });
+
+        let with = world.query::<(&Location, With<Size>)>();
+        assert_eq!(with.len(), 1);
+        assert_eq!(with[0].0.x, 20.0);
+
+        let without = world.query::<(&Location, Without<Size>)>();
+        assert_eq!(without.len(), 1);
+        assert_eq!(without[0].0.x, 10.0);
+
+        for (location, _) in world.query_mut::<(&mut Location, Without<Size>)>() {
+            location.x = 30.0;
+        }
+        let mut locations: Vec<f32> = world
+            .query::<&Location>()
+            .into_iter()
+            .map(|location| location.x)
+            .collect();
+        locations.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(locations, vec![20.0, 30.0]);
+    }
+
+    #[test]
+    fn test_query_optional_component() {
+        let mut world = World::new();
+        let first = world
+            .create_entity()
+            .with_component(Location { x: 10.0, y: 10.0 })
+            .entity();
+        let second = world
+            .create_entity()
+            .with_component(Size(40.0))
+            .with_component(Location { x: 20.0, y: 20.0 })
+            .entity();
+
+        let components = world.query::<(Entity, &Location, Option<&Size>)>();
+        assert_eq!(components.len(), 2);
+        for (entity, _, size) in components {
+            if entity == first {
+                assert_eq!(size, None);
+            } else {
+                assert_eq!(entity, second);
+                assert_eq!(size, Some(&Size(40.0)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_query_mut_optional_component() {
+        let mut world = World::new();
+        world
+            .create_entity()
+            .with_component(Location { x: 10.0, y: 10.0 });
+        world
+            .create_entity()
+            .with_component(Size(40.0))
+            .with_component(Location { x: 20.0, y: 20.0 });
+
+        for (location, size) in world.query_mut::<(&Location, Option<&mut Size>)>() {
+            if let Some(size) = size {
+                size.0 = location.x;
+            }
+        }
+
+        assert_eq!(world.query_mut::<(&Location, Option<&Size>)>().len(), 2);
+        assert_eq!(*world.query::<&Size>()[0], Size(20.0));
+    }
+
+    #[test]
+    fn test_query_tuple_eight() {
+        let mut world = World::new();
+        world
+            .create_entity()
+            .with_component(Location { x: 10.0, y: 10.0 })
+            .with_component(Size(40.0))
+            .with_component(true)
+            .with_component(1_u8)
+            .with_component(2_u16)
+            .with_component(3_u32)
+            .with_component(4_u64)
+            .with_component(5_i8);
+
+        let components = world.query::<(&Location, &Size, &bool, &u8, &u16, &u32, &u64, &i8)>();
+        assert_eq!(components.len(), 1);
+        assert_eq!(*components[0].7, 5);
+
+        let components = world.query_mut::<(
+            Entity,
+            &mut Location,
+            &Size,
+            &bool,
+            &u8,
+            &u16,
+            &u32,
+            &mut u64,
+        )>();
+        assert_eq!(components.len(), 1);
+        assert_eq!(*components[0].7, 4);
+    }
+
+    // Records what the change detection reports every time it runs.
+    #[derive(Default)]
+    struct ChangeRecorder {
+        added: Vec<Entity>,
+        changed: Vec<Entity>,
+        removed: Vec<Entity>,
+    }
+
+    impl System for ChangeRecorder {
+        fn update(&mut self, world: &mut World) {
+            self.added = world
+                .query::<(Entity, Added<Location>)>()
+                .into_iter()
+                .map(|(entity, _)| entity)
+                .collect();
+            self.changed = world
+                .query::<(Entity, Changed<Location>)>()
+                .into_iter()
+                .map(|(entity, _)| entity)
+                .collect();
+            self.removed = world.removed_components::<Location>();
+        }
+    }
+
+    #[test]
+    fn test_added_components() {
+        let mut world = World::new();
+        let mut system = ChangeRecorder::default();
+        let mut last_run = 0;
+        let first = world
+            .create_entity()
+            .with_component(Location { x: 10.0, y: 10.0 })
+            .entity();
+
+        world.run_system(&mut system, &mut last_run);
+        assert_eq!(system.added, vec![first]);
+
+        let second = world.create_entity().with_component(Size(10.0)).entity();
+        world.insert_component(second, Location { x: 20.0, y: 20.0 });
+
+        world.run_system(&mut system, &mut last_run);
+        assert_eq!(system.added, vec![second]);
+
+        world.run_system(&mut system, &mut last_run);
+        assert!(system.added.is_empty());
+    }
+
+    #[test]
+    fn test_changed_components() {
+        let mut world = World::new();
+        let mut system = ChangeRecorder::default();
+        let mut last_run = 0;
+        let first = world
+            .create_entity()
+            .with_component(Location { x: 10.0, y: 10.0 })
+            .entity();
+        let second = world
+            .create_entity()
+            .with_component(Location { x: 20.0, y: 20.0 })
+            .with_component(Size(10.0))
+            .entity();
+
+        world.run_system(&mut system, &mut last_run);
+        assert_eq!(system.changed.len(), 2);
+
+        // Only the components we query mutably are marked as changed
+        for (location, _) in world.query_mut::<(&mut Location, With<Size>)>() {
+            location.x = 30.0;
+        }
+        world.query_mut::<(&Location, Without<Size>)>();
+
+        world.run_system(&mut system, &mut last_run);
+        assert_eq!(system.changed, vec![second]);
+
+        world.insert_component(first, Location { x: 40.0, y: 40.0 });
+
+        world.run_system(&mut system, &mut last_run);
+        assert_eq!(system.changed, vec![first]);
+        assert!(system.added.is_empty());
+
+        world.get_mut::<Location>(second).unwrap().y = 50.0;
+        let third = world.create_entity().entity();
+        assert!(world.get_mut::<Location>(third).is_none());
+
+        world.run_system(&mut system, &mut last_run);
+        assert_eq!(system.changed, vec![second]);
+    }
+
+    #[test]
+    fn test_removed_components() {
+        let mut world = World::new();
+        let mut system = ChangeRecorder::default();
+        let mut last_run = 0;
+        let first = world
+            .create_entity()
+            .with_component(Location { x: 10.0, y: 10.0 })
+            .entity();
+        let second = world
+            .create_entity()
+            .with_component(Location { x: 20.0, y: 20.0 })
+            .entity();
+
+        world.run_system(&mut system, &mut last_run);
+        assert!(system.removed.is_empty());
+
+        world.remove_component::<Location>(first);
+        world.remove_entity(second);
+
+        world.run_system(&mut system, &mut last_run);
+        assert_eq!(system.removed, vec![first, second]);
+
+        world.run_system(&mut system, &mut last_run);
+        assert!(system.removed.is_empty());
+    }
+
     #[test]
     fn test_create_entity_with_component() {
         let mut world = World::new();
-        world.register_component::<Location>();
-        world.register_component::<Size>();
         world
             .create_entity()
             .with_component(Location { x: 1.0, y: 1.0 })
@@ -231,12 +781,78 @@ mod test {
         assert_eq!(*locations[0], Location { x: 1.0, y: 1.0 });
     }
 
+    #[test]
+    fn test_get_component() {
+        let mut world = World::new();
+        let first = world
+            .create_entity()
+            .with_component(Location { x: 10.0, y: 10.0 })
+            .entity();
+        let second = world
+            .create_entity()
+            .with_component(Location { x: 20.0, y: 20.0 })
+            .entity();
+
+        assert_eq!(world.get::<Location>(second).unwrap().x, 20.0);
+        assert_eq!(world.get::<Size>(second), None);
+
+        world.get_mut::<Location>(first).unwrap().x = 30.0;
+        assert_eq!(world.get::<Location>(first).unwrap().x, 30.0);
+        assert_eq!(world.get::<Location>(second).unwrap().x, 20.0);
+
+        world.remove_entity(first);
+        assert_eq!(world.get::<Location>(first), None);
+        assert_eq!(world.get_mut::<Location>(first), None);
+    }
+
+    #[test]
+    fn test_insert_component_for_existing_entity() {
+        let mut world = World::new();
+        let first = world
+            .create_entity()
+            .with_component(Location { x: 10.0, y: 10.0 })
+            .entity();
+        world
+            .create_entity()
+            .with_component(Location { x: 20.0, y: 20.0 });
+
+        assert!(!world.has::<Size>(first));
+        world.insert_component(first, Size(40.0));
+        assert!(world.has::<Size>(first));
+        assert!(world.has::<Location>(first));
+
+        let (entity, size) = world.query::<(Entity, &Size)>()[0];
+        assert_eq!(entity, first);
+        assert_eq!(*size, Size(40.0));
+    }
+
+    #[test]
+    fn test_remove_entity() {
+        let mut world = World::new();
+        let first = world
+            .create_entity()
+            .with_component(Location { x: 10.0, y: 10.0 })
+            .entity();
+        let second = world
+            .create_entity()
+            .with_component(Location { x: 20.0, y: 20.0 })
+            .entity();
+
+        world.remove_entity(first);
+
+        let locations = world.query::<&Location>();
+        assert_eq!(locations.len(), 1);
+        assert_eq!(locations[0].x, 20.0);
+        assert!(!world.is_alive(first));
+        assert!(world.is_alive(second));
+    }
+
     #[test]
 
     fn test_get_resource() {
         let mut world = World::new();
         let id = TypeId::of::<bool>();
-        world.resources.insert(id, Box::new(true));
+        world.resources.insert(id, RefCell::new(Box::new(true)));
 
         let resource = world.get_resource::<bool>().unwrap();
         assert_eq!(*resource, true);
@@ -246,9 +862,9 @@ mod test {
     fn test_get_resource_mut() {
         let mut world = World::new();
         let id = TypeId::of::<usize>();
-        world.resources.insert(id, Box::new(1_usize));
+        world.resources.insert(id, RefCell::new(Box::new(1_usize)));
 
-        let resource = world.get_resource_mut::<usize>().unwrap();
+        let mut resource = world.get_resource_mut::<usize>().unwrap();
         *resource += 1;
         assert_eq!(*resource, 2);
     }
@@ -261,4 +877,170 @@ mod test {
         let resource = world.get_resource::<usize>().unwrap();
         assert_eq!(*resource, 10);
     }
+
+    #[test]
+    fn test_get_several_resources_mut() {
+        let mut world = World::new();
+        world.create_resource(1_usize);
+        world.create_resource(true);
+
+        let mut first = world.get_resource_mut::<usize>().unwrap();
+        let mut second = world.get_resource_mut::<bool>().unwrap();
+        *first += 1;
+        *second = false;
+
+        assert_eq!(*first, 2);
+        assert!(!*second);
+    }
+
+    #[test]
+    fn test_get_resource_while_querying() {
+        let mut world = World::new();
+        world.create_resource(0.0_f32);
+        world.create_entity().with_component(Size(10.0));
+        world.create_entity().with_component(Size(20.0));
+
+        let mut total = world.get_resource_mut::<f32>().unwrap();
+        for size in world.query::<&Size>() {
+            *total += size.0;
+        }
+
+        assert_eq!(*total, 30.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "resource `usize` is already borrowed")]
+    fn test_get_resource_mut_twice() {
+        let mut world = World::new();
+        world.create_resource(1_usize);
+
+        let _first = world.get_resource_mut::<usize>().unwrap();
+        let _second = world.get_resource_mut::<usize>().unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "resource `usize` is already borrowed mutably")]
+    fn test_get_resource_while_borrowed_mutably() {
+        let mut world = World::new();
+        world.create_resource(1_usize);
+
+        let _first = world.get_resource_mut::<usize>().unwrap();
+        let _second = world.get_resource::<usize>().unwrap();
+    }
+
+    #[test]
+    fn test_events() {
+        let mut world = World::new();
+        world.add_event::<Size>();
+        let mut reader = EventReader::<Size>::new();
+
+        world.send_event(Size(10.0));
+        world.update_events();
+        world.send_event(Size(20.0));
+
+        let events = world.get_resource::<Events<Size>>().unwrap();
+        assert_eq!(reader.read(&events), vec![&Size(10.0), &Size(20.0)]);
+        drop(events);
+
+        world.send_event(Size(30.0));
+        world.update_events();
+        world.update_events();
+        let events = world.get_resource::<Events<Size>>().unwrap();
+        assert!(reader.read(&events).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "has not been added")]
+    fn test_send_event_without_adding() {
+        let world = World::new();
+        world.send_event(Size(10.0));
+    }
+
+    fn create_registered_world() -> World {
+        let mut world = World::new();
+        world.register_component::<Location>("Location");
+        world.register_component::<Size>("Size");
+        world.register_resource::<usize>("Count");
+        world
+    }
+
+    #[test]
+    fn test_snapshot_and_restore() {
+        let mut world = create_registered_world();
+        world.create_resource(10_usize);
+        let first = world
+            .create_entity()
+            .with_component(Location { x: 10.0, y: 10.0 })
+            .with_component(true)
+            .entity();
+        let second = world
+            .create_entity()
+            .with_component(Location { x: 20.0, y: 20.0 })
+            .with_component(Size(40.0))
+            .entity();
+
+        let snapshot = world.snapshot();
+
+        world.remove_entity(first);
+        world.get_mut::<Size>(second).unwrap().0 = 50.0;
+        let third = world.create_entity().with_component(Size(60.0)).entity();
+        world.create_resource(20_usize);
+
+        world.restore(&snapshot).unwrap();
+
+        assert_eq!(
+            world.get::<Location>(first),
+            Some(&Location { x: 10.0, y: 10.0 })
+        );
+        assert_eq!(world.get::<Size>(second), Some(&Size(40.0)));
+        assert_eq!(world.get_resource::<usize>().as_deref(), Some(&10));
+        // Unregistered components are left out
+        assert!(world.is_alive(first));
+        assert!(!world.has::<bool>(first));
+        // The third entity reused the slot of the first one, so it's gone now
+        assert!(!world.is_alive(third));
+        assert_eq!(world.query::<Entity>().len(), 2);
+
+        assert_eq!(world.snapshot(), snapshot);
+    }
+
+    #[test]
+    fn test_restore_into_new_world() {
+        let mut world = create_registered_world();
+        world.create_entity();
+        let entity = world.create_entity().with_component(Size(40.0)).entity();
+        world.remove_entity(entity);
+        let entity = world.create_entity().with_component(Size(50.0)).entity();
+
+        let mut restored = create_registered_world();
+        restored.restore(&world.snapshot()).unwrap();
+
+        assert_eq!(restored.get::<Size>(entity), Some(&Size(50.0)));
+        assert_eq!(restored.snapshot(), world.snapshot());
+    }
+
+    #[test]
+    fn test_restore_invalid_snapshot() {
+        let mut world = create_registered_world();
+        let entity = world.create_entity().with_component(Size(40.0)).entity();
+
+        assert!(world.restore("not json").is_err());
+
+        let unknown = r#"{"entities":[{"entity":{"index":0,"generation":0},"components":{"Mass":1.0}}],"resources":{}}"#;
+        assert_eq!(
+            world.restore(unknown),
+            Err(String::from("unknown component `Mass`"))
+        );
+
+        let invalid = r#"{"entities":[{"entity":{"index":0,"generation":0},"components":{"Size":"big"}}],"resources":{}}"#;
+        assert!(world.restore(invalid).is_err());
+
+        let out_of_range = r#"{"entities":[{"entity":{"index":1000000000,"generation":0},"components":{}}],"resources":{}}"#;
+        assert_eq!(
+            world.restore(out_of_range),
+            Err(String::from("entity index 1000000000 is out of range"))
+        );
+
+        assert_eq!(world.get::<Size>(entity), Some(&Size(40.0)));
+    }
 }